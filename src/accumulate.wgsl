@@ -0,0 +1,21 @@
+#include "src/fullscreen.wgsl"
+
+@group(0) @binding(0)
+var frame_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var frame_texture_sampler: sampler;
+@group(0) @binding(2)
+var history_texture: texture_2d<f32>;
+@group(0) @binding(3)
+var history_texture_sampler: sampler;
+// how much of this frame goes into the average, 1 / the number of frames in it
+@group(0) @binding(4)
+var<uniform> weight: f32;
+
+@fragment
+fn main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the textures are the same size as the output so this is the exact texel
+    let frame = textureSampleLevel(frame_texture, frame_texture_sampler, in.texcoord, 0.0);
+    let history = textureSampleLevel(history_texture, history_texture_sampler, in.texcoord, 0.0);
+    return mix(history, frame, weight);
+}
@@ -0,0 +1,321 @@
+// renders one still image without opening a window
+// cargo run --release --bin render -- --pos 0,2,-20 --fov 70 --set "kerr metric=true" --output hole.png
+// or, with --path, a numbered png sequence following a camera path (see camera_path.rs)
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+use black_hole_ray_marching::{
+    bloom::BloomSettings,
+    camera_path::{parse_vec3, CameraPath},
+    headless::HeadlessRenderer,
+    scene::SceneBackend,
+    tone_mapping::ToneMappingCurve,
+};
+use glam::Vec3;
+
+const USAGE: &str = "\
+usage: render [options]
+
+  --output <path>          where to save the image, .png or .exr (default render.png)
+                           an exr is the linear image before exposure and tone mapping
+  --width <pixels>         (default 1920)
+  --height <pixels>        (default 1080)
+  --pos <x,y,z>            camera position (default 0,0,-20)
+  --dir <x,y,z>            camera direction (default towards the black hole)
+  --look-at <x,y,z>        point the camera at this instead of giving a direction
+  --up <x,y,z>             made perpendicular to the direction (default 0,1,0)
+  --fov <degrees>          vertical field of view (default 90)
+  --bloom-levels <n>       (default 3)
+  --bloom-threshold <x>    colours dimmer than this don't bloom (default 1)
+  --bloom-intensity <x>    how much bloom is added to the image (default 0.5)
+  --bloom-radius <x>       blur sample offset in half pixels (default 3)
+  --bloom-weights <a,b,..> how much each level's blur is mixed in (default 0.5 each)
+  --tone-mapping <curve>   linear, reinhard, aces or agx (default aces)
+  --exposure <stops>       (default 0)
+  --time <seconds>         how far the volumetric disk has turned (default 0)
+  --samples <n>            jittered frames averaged into each image, for anti-aliasing
+                           and less noise (default 1)
+  --path <file>            render every frame of a camera path instead of one still,
+                           output.png becomes output_0000.png, output_0001.png, ...
+                           the path sets the camera, so --pos, --dir, --look-at and
+                           --up can't be given with it
+  --fps <n>                frame rate for --path (default 30)
+  --set <uniform>=<value>  set a shader uniform by its label or number, can be repeated
+  --list-uniforms          print every uniform with its number and default and exit
+  --backend <backend>      fragment or compute, compute falls back to fragment where it isn't
+                           supported (default fragment)
+  --software               use the software adapter even if there's a gpu
+  --profile                print how long each pass took on the gpu for every image
+  --help                   print this
+";
+
+struct Args {
+    output: PathBuf,
+    width: u32,
+    height: u32,
+    pos: Option<Vec3>,
+    dir: Option<Vec3>,
+    look_at: Option<Vec3>,
+    up: Option<Vec3>,
+    fov: f32,
+    bloom: BloomSettings,
+    tone_mapping: ToneMappingCurve,
+    exposure: f32,
+    time: f32,
+    samples: u32,
+    path: Option<PathBuf>,
+    fps: f32,
+    uniforms: Vec<(String, String)>,
+    list_uniforms: bool,
+    backend: SceneBackend,
+    software: bool,
+    profile: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            output: PathBuf::from("render.png"),
+            width: 1920,
+            height: 1080,
+            pos: None,
+            dir: None,
+            look_at: None,
+            up: None,
+            fov: 90.0,
+            bloom: BloomSettings::default(),
+            tone_mapping: ToneMappingCurve::AcesFilmic,
+            exposure: 0.0,
+            time: 0.0,
+            samples: 1,
+            path: None,
+            fps: 30.0,
+            uniforms: vec![],
+            list_uniforms: false,
+            backend: SceneBackend::default(),
+            software: false,
+            profile: false,
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, s: &str) -> anyhow::Result<T> {
+    s.parse()
+        .map_err(|_| anyhow!("\"{}\" isn't a valid number for {}", s, flag))
+}
+
+impl Args {
+    // returns none if only the usage should be printed
+    fn parse(mut raw: impl Iterator<Item = String>) -> anyhow::Result<Option<Self>> {
+        let mut args = Self::default();
+        while let Some(flag) = raw.next() {
+            let mut value = || raw.next().ok_or_else(|| anyhow!("{} needs a value", flag));
+            match flag.as_str() {
+                "--output" | "-o" => args.output = PathBuf::from(value()?),
+                "--width" => args.width = parse_number(&flag, &value()?)?,
+                "--height" => args.height = parse_number(&flag, &value()?)?,
+                "--pos" => args.pos = Some(parse_vec3(&value()?)?),
+                "--dir" => args.dir = Some(parse_vec3(&value()?)?),
+                "--look-at" => args.look_at = Some(parse_vec3(&value()?)?),
+                "--up" => args.up = Some(parse_vec3(&value()?)?),
+                "--fov" => args.fov = parse_number(&flag, &value()?)?,
+                "--bloom-levels" => args.bloom.levels = parse_number(&flag, &value()?)?,
+                "--bloom-threshold" => args.bloom.threshold = parse_number(&flag, &value()?)?,
+                "--bloom-intensity" => args.bloom.intensity = parse_number(&flag, &value()?)?,
+                "--bloom-radius" => args.bloom.radius = parse_number(&flag, &value()?)?,
+                "--bloom-weights" => {
+                    args.bloom.level_weights = value()?
+                        .split(',')
+                        .map(|weight| parse_number(&flag, weight.trim()))
+                        .collect::<anyhow::Result<_>>()?;
+                }
+                "--tone-mapping" => {
+                    let name = value()?;
+                    args.tone_mapping = ToneMappingCurve::from_name(&name).ok_or_else(|| {
+                        anyhow!("\"{}\" isn't linear, reinhard, aces or agx", name)
+                    })?;
+                }
+                "--exposure" => args.exposure = parse_number(&flag, &value()?)?,
+                "--time" => args.time = parse_number(&flag, &value()?)?,
+                "--samples" => args.samples = parse_number(&flag, &value()?)?,
+                "--path" => args.path = Some(PathBuf::from(value()?)),
+                "--fps" => args.fps = parse_number(&flag, &value()?)?,
+                "--set" => {
+                    let assignment = value()?;
+                    let (name, value) = assignment.split_once('=').ok_or_else(|| {
+                        anyhow!("--set needs <uniform>=<value>, not \"{}\"", assignment)
+                    })?;
+                    args.uniforms.push((name.to_owned(), value.to_owned()));
+                }
+                "--list-uniforms" => args.list_uniforms = true,
+                "--backend" => {
+                    let name = value()?;
+                    args.backend = SceneBackend::from_name(&name)
+                        .ok_or_else(|| anyhow!("\"{}\" isn't fragment or compute", name))?;
+                }
+                "--software" => args.software = true,
+                "--profile" => args.profile = true,
+                "--help" | "-h" => return Ok(None),
+                _ => bail!("unknown argument \"{}\"\n\n{}", flag, USAGE),
+            }
+        }
+        if args.dir.is_some() && args.look_at.is_some() {
+            bail!("--dir and --look-at can't both be given");
+        }
+        let sets_camera =
+            args.pos.is_some() || args.dir.is_some() || args.look_at.is_some() || args.up.is_some();
+        if args.path.is_some() && sets_camera {
+            bail!("--pos, --dir, --look-at and --up can't be given with --path, the path sets the camera");
+        }
+        if args.bloom.levels == 0 {
+            bail!("--bloom-levels has to be at least 1");
+        }
+        if args.fps.is_nan() || args.fps <= 0.0 {
+            bail!("--fps has to be more than 0");
+        }
+        if args.samples == 0 {
+            bail!("--samples has to be at least 1");
+        }
+        Ok(Some(args))
+    }
+
+    fn pos(&self) -> Vec3 {
+        self.pos.unwrap_or(Vec3::new(0.0, 0.0, -20.0))
+    }
+
+    fn dir(&self) -> anyhow::Result<Vec3> {
+        let dir = match (self.dir, self.look_at) {
+            (Some(dir), _) => dir,
+            (None, Some(look_at)) => look_at - self.pos(),
+            (None, None) => -self.pos(),
+        };
+        dir.try_normalize()
+            .ok_or_else(|| anyhow!("the camera direction can't be zero"))
+    }
+
+    // --up with the part along the direction taken out, so the camera basis isn't skewed
+    fn up(&self, dir: Vec3) -> anyhow::Result<Vec3> {
+        let up = self.up.unwrap_or(Vec3::Y);
+        dir.cross(up.cross(dir))
+            .try_normalize()
+            .ok_or_else(|| anyhow!("--up can't be zero or parallel to the camera direction"))
+    }
+}
+
+// frame.png -> frame_0042.png
+fn frame_path(output: &Path, frame: usize) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map_or("frame".into(), |stem| stem.to_string_lossy());
+    let file_name = match output.extension() {
+        Some(extension) => format!("{}_{:04}.{}", stem, frame, extension.to_string_lossy()),
+        None => format!("{}_{:04}", stem, frame),
+    };
+    output.with_file_name(file_name)
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let Some(args) = Args::parse(std::env::args().skip(1))? else {
+        print!("{}", USAGE);
+        return Ok(());
+    };
+
+    let mut renderer = pollster::block_on(HeadlessRenderer::new(
+        args.width,
+        args.height,
+        args.bloom.clone(),
+        args.backend,
+        args.software,
+    ))?;
+
+    let other_uniforms = &mut renderer.scene.other_uniforms;
+    if args.list_uniforms {
+        for (i, other_uniform) in other_uniforms.other_uniforms.iter().enumerate() {
+            println!(
+                "{:>2}  {} = {}",
+                i,
+                other_uniform.label,
+                other_uniform.value_to_string()
+            );
+        }
+        return Ok(());
+    }
+    for (name, value) in &args.uniforms {
+        other_uniforms
+            .find_mut(name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "there's no uniform called \"{}\", see --list-uniforms",
+                    name
+                )
+            })?
+            .set_from_str(value)
+            .map_err(|error| anyhow!(error))?;
+    }
+    renderer.scene.write_other_uniforms(&renderer.queue);
+    let tone_mapping = &mut renderer.post_processing.tone_mapping;
+    tone_mapping.curve = args.tone_mapping;
+    tone_mapping.exposure = args.exposure;
+    tone_mapping.write_uniform(&renderer.queue);
+    if args.samples > 1 {
+        renderer
+            .post_processing
+            .set_progressive(&renderer.device, true);
+    }
+    renderer.set_profiling(args.profile)?;
+
+    if let Some(path) = &args.path {
+        let camera_path = CameraPath::load(path)?;
+        let frame_count = (camera_path.duration() * args.fps).floor() as usize + 1;
+        if let Some(parent) = args
+            .output
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // the first update only sets the time the disk starts at
+        let mut delta_time = Duration::from_secs_f32(args.time.max(0.0));
+        for frame in 0..frame_count {
+            let time = camera_path.start_time() + frame as f32 / args.fps;
+            camera_path.apply(time, &mut renderer.scene.camera);
+            renderer.update(delta_time);
+            renderer.accumulate(args.samples - 1);
+            let output = frame_path(&args.output, frame);
+            renderer.render_to_file(&output)?;
+            println!("saved {} ({}/{})", output.display(), frame + 1, frame_count);
+            print_profile(&renderer);
+            delta_time = Duration::from_secs_f32(1.0 / args.fps);
+        }
+        return Ok(());
+    }
+
+    let camera = &mut renderer.scene.camera;
+    camera.pos = args.pos();
+    camera.dir = args.dir()?;
+    camera.up = args.up(camera.dir)?;
+    camera.fovy = args.fov.to_radians();
+
+    // the camera controller has no input, so this just uploads the camera and sets the time
+    renderer.update(Duration::from_secs_f32(args.time.max(0.0)));
+    renderer.accumulate(args.samples - 1);
+    renderer.render_to_file(&args.output)?;
+    println!("saved {}", args.output.display());
+    print_profile(&renderer);
+
+    Ok(())
+}
+
+// the gpu time of each pass in the last image, if --profile was given
+fn print_profile(renderer: &HeadlessRenderer) {
+    if let Some(profiler) = &renderer.profiler {
+        println!("{}", profiler.table());
+    }
+}
@@ -0,0 +1,38 @@
+// the compute backend's entry point
+// it writes what fs_main would, one workgroup per tile of the output
+
+// leaves out vs_main and fs_main
+#define COMPUTE
+#include "src/black_hole_maybe.wgsl"
+
+const TILE_SIZE = 8u;
+
+@group(2) @binding(0)
+var output_texture: texture_storage_2d<rgba16float, write>;
+@group(2) @binding(1)
+var blackout_output_texture: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(TILE_SIZE, TILE_SIZE)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output_texture);
+    // the tiles on the right and bottom edges hang off the texture
+    if any(id.xy >= size) {
+        return;
+    }
+
+    // where the centre of this pixel is on the screen triangle vs_main draws
+    let texcoord = (vec2f(id.xy) + 0.5) / vec2f(size);
+    let clip = vec2f(texcoord.x * 2.0 - 1.0, 1.0 - texcoord.y * 2.0);
+    // its corners are (3, 1), (-1, 1) and (-1, -3), and the direction is linear over it
+    let s = (clip.x + 1.0) * 0.25;
+    let t = (1.0 - clip.y) * 0.25;
+    let corner = camera.pos_to_world_space_screen_triangle[1].xyz;
+    let camera_to_pixel = corner
+        + (camera.pos_to_world_space_screen_triangle[0].xyz - corner) * s
+        + (camera.pos_to_world_space_screen_triangle[2].xyz - corner) * t;
+
+    let photon = Photon(camera.pos.xyz, normalize(camera_to_pixel));
+    let col = vec4<f32>(get_col(photon), 1.0);
+    textureStore(output_texture, id.xy, col);
+    textureStore(blackout_output_texture, id.xy, col);
+}
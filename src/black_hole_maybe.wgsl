@@ -14,6 +14,8 @@ struct Camera {
  // has to be vec4 for correct array stride 
   screen_space_screen_triangle: array<vec4<f32>, 3>,
   pos_to_world_space_screen_triangle: array<vec4<f32>, 3>,
+  // seconds since the start
+  time: f32,
 }
 @group(0) @binding(0) // 1.
 var<uniform> camera: Camera;
@@ -28,6 +30,8 @@ struct VertexOutput {
     @location(1) camera_to_vertex: vec3<f32>,
 }
 
+// black_hole_compute.wgsl has its own entry point instead
+#ifndef COMPUTE
 // var<private> positions: array<vec2f, 3> = array<vec2f, 3>(
 //     vec2f(3.0, 1.0),
 //     vec2f(-1.0, 1.0),
@@ -53,19 +57,36 @@ fn vs_main(
   // out.camera_to_vertex = vec3f(0.0);
     return out;
 }
+#endif
 
 // Fragment shader
 struct Uniforms {
     // 0 bytes
     RS: f32,
-    DELTA_TIME_MULT: f32,
+    LOG_TOLERANCE: f32,
     BG_BRIGHTNESS: f32,
     BLACKOUT_EH: u32,
     MAX_DIST: f32,
     DISTORTION_POWER: f32,
     // 24 bytes
-    _padding: vec2<u32>,
-    // 32 bytes (16x2)
+    KERR: u32,
+    SPIN: f32,
+    // 32 bytes
+    INTEGRATOR: u32,
+    DISK_INNER_RADIUS: f32,
+    DISK_OUTER_RADIUS: f32,
+    ACCRETION_RATE: f32,
+    // 48 bytes
+    DISK_INCLINATION: f32,
+    REDSHIFT: u32,
+    BEAMING_EXPONENT: f32,
+    VOLUMETRIC: u32,
+    // 64 bytes
+    DISK_SCALE_HEIGHT: f32,
+    DISK_TURBULENCE: f32,
+    DISK_OPACITY: f32,
+    DEFLECTION_LUT: u32,
+    // 80 bytes (16x5)
 }
 
 @group(0) @binding(1)
@@ -75,6 +96,11 @@ var<uniform> u: Uniforms;
 var t_diffuse: texture_2d<f32>;
 @group(1) @binding(1)
 var s_diffuse: sampler;
+// see deflection_lut.rs
+@group(1) @binding(2)
+var deflection_lut: texture_2d<f32>;
+@group(1) @binding(3)
+var orbit_table: texture_2d<f32>;
 
 const BH_POS: vec3f = vec3f(0.0);
 const MIN_DIST = 0.001;
@@ -83,27 +109,34 @@ const TWO_PI = 6.28318530718;
 const ONE_PI = 3.14159265359;
 const HALF_PI = 1.57079632679;
 const MAX_ITERATIONS = 1000;
+// fixed step rk4 moves this fraction of the distance to the singularity each step
+const DELTA_TIME_MULT = 0.5;
+
+const INTEGRATOR_RK4 = 0u;
+const INTEGRATOR_RK45 = 1u;
+const INTEGRATOR_BINET = 2u;
+
+// peak temperature in kelvin of a disk around a non spinning black hole at an accretion rate of 1
+const DISK_PEAK_TEMPERATURE = 6500.0;
+// peak of novikov_thorne_flux(r, 6, 0), which is at r ~ 9.55
+const DISK_REFERENCE_FLUX = 1.3678e-5;
+// how bright the hottest part of that disk is, > 1 so it blooms
+const DISK_BRIGHTNESS = 3.0;
+// inside the volumetric disk each step is at most this fraction of the scale height
+const VOLUME_STEP = 0.5;
+// the volumetric disk is cut off this many scale heights above and below the middle
+const VOLUME_HEIGHT = 3.0;
+// how many times faster than real time (in units of r_s / c) the gas in the volumetric disk orbits
+const DISK_TIME_SCALE = 4.0;
+
+#include "src/sdf.wgsl"
+#include "src/integrator.wgsl"
+#include "src/colour.wgsl"
 
 fn u32_to_bool(n: u32) -> bool {
     return n != 0u;
 }
 
-fn sdf_sphere(p: vec3<f32>, centre: vec3<f32>, r: f32) -> f32 {
-    return length(centre - p) - r;
-}
-
-fn sdf_plane(p: vec3<f32>, y: f32) -> f32 {
-    return abs(p.y - y) - 0.02;
-}
-
-fn sdf_cylinder(p: vec3<f32>, pos: vec2<f32>, radius: f32) -> f32 {
-    return length(p.xz - pos) - radius;
-}
-
-fn sdf_accretion_disk(p: vec3<f32>, centre: vec3<f32>, big_r: f32, little_r: f32) -> f32 {
-    return max(max(sdf_cylinder(p, centre.xz, big_r), -sdf_cylinder(p, centre.xz, little_r)), sdf_plane(p, centre.y));
-}
-
 fn sdf_markers(p: vec3f) -> f32 {
     let sd_sphere_1 = sdf_sphere(p, vec3<f32>(0.0, 10.0, -10.0), 0.5);
     let sd_sphere_2 = sdf_sphere(p, vec3<f32>(0.0, -10.0, -10.0), 0.5);
@@ -116,61 +149,245 @@ fn sdf_markers(p: vec3f) -> f32 {
     return min(sd_sphere_1, min(sd_sphere_2, min(sd_sphere_3, sd_sphere_4)));
 }
 
-fn sdf(p: vec3<f32>) -> f32 {
-    let sd_accretion_disk = sdf_accretion_disk(p, vec3<f32>(0.0), 6.0 * u.RS, 3.0 * u.RS);
-    let sd_markers = sdf_markers(p);
-  return min(sd_accretion_disk, sd_markers);
+// every marker is 10 * sqrt(2) from the black hole with a radius of 0.5
+const MARKERS_RADIUS = 14.65;
+
+// spin the disk sees, schwarzschild mode has none
+fn disk_spin() -> f32 {
+    if u32_to_bool(u.KERR) {
+        return kerr_spin();
+    }
+    return 0.0;
 }
 
-fn rd_derivative(ro: vec3<f32>, h2: f32) -> vec3<f32> {
-    return u.DISTORTION_POWER * u.RS * -1.5 * h2 * ro / pow(dot(ro, ro), 2.5);
+// inner edge of the accretion disk in boyer-lindquist r, as a multiple of the isco
+// for schwarzschild the isco is at 3 * r_s
+// for kerr the isco moves in (prograde) as the spin goes up
+fn disk_inner_r() -> f32 {
+    return max(u.DISK_INNER_RADIUS, 1.0) * isco_radius(black_hole_mass(), disk_spin());
 }
 
-struct Photon {
-    ro: vec3<f32>,
-    rd: vec3<f32>,
+// the sdf works in cylindrical radius,
+// so for kerr the boyer-lindquist radius has to be converted to the kerr-schild one
+fn disk_inner_radius() -> f32 {
+    let r_in = disk_inner_r();
+    if u32_to_bool(u.KERR) {
+        let a = kerr_a();
+        return sqrt(r_in * r_in + a * a);
+    }
+    return r_in;
 }
 
-fn get_delta_photon_rk4(photon: Photon, delta_time: f32, h2: f32) -> Photon {
-    let ro_k1 = delta_time * photon.rd;
-    let rd_k1 = delta_time * rd_derivative(photon.ro, h2);
-    
-    let ro_k2 = delta_time * (photon.rd + 0.5 * rd_k1);
-    let rd_k2 = delta_time * rd_derivative(photon.ro + 0.5 * ro_k1, h2);
-    
-    let ro_k3 = delta_time * (photon.rd + 0.5 * rd_k2);
-    let rd_k3 = delta_time * rd_derivative(photon.ro + 0.5 * ro_k2, h2);
-    
-    let ro_k4 = delta_time * (photon.rd + rd_k3);
-    let rd_k4 = delta_time * rd_derivative(photon.ro + ro_k3, h2);
+fn sdf_disk(p: vec3<f32>) -> f32 {
+    return sdf_accretion_disk(p, vec3<f32>(0.0), u.DISK_OUTER_RADIUS * u.RS, disk_inner_radius());
+}
 
-    let delta_ro = (ro_k1 + 2.0 * ro_k2 + 2.0 * ro_k3 + ro_k4) / 6.0;
-    let delta_rd = (rd_k1 + 2.0 * rd_k2 + 2.0 * rd_k3 + rd_k4) / 6.0;
+// the black hole and the disk can be tilted about the x axis,
+// the markers and the background stay where they are
+fn to_disk_frame(v: vec3<f32>) -> vec3<f32> {
+    return rotate_vector(v, vec3<f32>(1.0, 0.0, 0.0), -radians(u.DISK_INCLINATION));
+}
 
-    return Photon(delta_ro, delta_rd);
+fn from_disk_frame(v: vec3<f32>) -> vec3<f32> {
+    return rotate_vector(v, vec3<f32>(1.0, 0.0, 0.0), radians(u.DISK_INCLINATION));
 }
-  
-fn rotate_vector(vector: vec3f, unit_axis: vec3f, angle: f32) -> vec3f {
-    let cos_theta = cos(angle);
-    let sin_theta = sin(angle);
-    // Calculate the rotation matrix components
-    let ux = unit_axis.x;
-    let uy = unit_axis.y;
-    let uz = unit_axis.z;
-    let one_minus_cos = 1.0 - cos_theta;
-
-    // Apply the rotation formula
-    let x = vector.x * (cos_theta + ux * ux * one_minus_cos) + 
-            vector.y * (ux * uy * one_minus_cos - uz * sin_theta) + 
-            vector.z * (ux * uz * one_minus_cos + uy * sin_theta);
-    let y = vector.x * (uy * ux * one_minus_cos + uz * sin_theta) + 
-            vector.y * (cos_theta + uy * uy * one_minus_cos) + 
-            vector.z * (uy * uz * one_minus_cos - ux * sin_theta);
-    let z = vector.x * (uz * ux * one_minus_cos - uy * sin_theta) + 
-            vector.y * (uz * uy * one_minus_cos + ux * sin_theta) + 
-            vector.z * (cos_theta + uz * uz * one_minus_cos);
-
-    return vec3<f32>(x, y, z);
+
+// accretion disk emission
+// a thin disk (shakura & sunyaev 1973) radiates away the energy released by the gas spiralling in,
+// and each ring of the disk radiates as a blackbody
+// the general relativistic version of the flux is novikov & thorne 1973, closed form from page & thorne 1974
+
+// flux radiated from one face of the disk at boyer-lindquist radius r,
+// in units where M = 1 and the accretion rate is 1
+// the disk is assumed to have no torque at its inner edge r_in
+fn novikov_thorne_flux(r: f32, r_in: f32, spin: f32) -> f32 {
+    if r <= r_in {
+        return 0.0;
+    }
+    let a = spin;
+    let x = sqrt(r);
+    let x0 = sqrt(r_in);
+    // roots of x^3 - 3x + 2a = 0
+    let acos_a = acos(a);
+    let x1 = 2.0 * cos((acos_a - ONE_PI) / 3.0);
+    let x2 = 2.0 * cos((acos_a + ONE_PI) / 3.0);
+    let x3 = -2.0 * cos(acos_a / 3.0);
+
+    let bracket = x - x0 - 1.5 * a * log(x / x0)
+        - novikov_thorne_log_term(x, x0, a, x1, x2, x3)
+        - novikov_thorne_log_term(x, x0, a, x2, x3, x1)
+        - novikov_thorne_log_term(x, x0, a, x3, x1, x2);
+    let xx = x * x;
+    return 3.0 / (8.0 * ONE_PI) * bracket / (xx * xx * (xx * x - 3.0 * x + 2.0 * a));
+}
+
+fn novikov_thorne_log_term(x: f32, x0: f32, a: f32, xi: f32, xj: f32, xk: f32) -> f32 {
+    // for a = 0 one of the roots is 0 and this is 0 / 0, but it goes to 0
+    if abs(xi) < 1e-4 {
+        return 0.0;
+    }
+    return 3.0 * (xi - a) * (xi - a) / (xi * (xi - xj) * (xi - xk)) * log((x - xi) / (x0 - xi));
+}
+
+// temperature goes as flux^(1/4) (stefan-boltzmann)
+fn disk_temperature(flux: f32) -> f32 {
+    return DISK_PEAK_TEMPERATURE * pow(max(flux, 0.0) / DISK_REFERENCE_FLUX, 0.25);
+}
+
+// angular velocity d(phi)/dt of a circular orbit at boyer-lindquist radius r
+// the disk turns with the black hole, +y for positive spin
+fn keplerian_omega(r: f32, m: f32, spin: f32) -> f32 {
+    var orbit_sign = 1.0;
+    if spin < 0.0 {
+        orbit_sign = -1.0;
+    }
+    let sqrt_m = sqrt(m);
+    return orbit_sign * sqrt_m / (r * sqrt(r) + abs(spin) * m * sqrt_m);
+}
+
+// redshift g = E_observed / E_emitted of light that left the disk at boyer-lindquist radius r
+// the gas is on prograde circular (keplerian) orbits and the observer is at rest far away
+// with the photon energy fixed at 1 this is g = 1 / (u^t (1 - omega p_phi))
+// where p_phi is the angular momentum of the photon about the spin axis, which doesn't change along the ray
+fn disk_redshift(photon: Photon, r: f32, m: f32, spin: f32) -> f32 {
+    let a = abs(spin) * m;
+    // the traced photon goes the opposite way to the real one, so its angular momentum is flipped
+    // in schwarzschild mode photon.rd started off as a unit vector, so this works there too
+    let p_phi = -cross(photon.ro, photon.rd).y;
+    let sqrt_m = sqrt(m);
+    let r_3_2 = r * sqrt(r);
+    let omega = keplerian_omega(r, m, spin);
+    let u_t = (r_3_2 + a * sqrt_m) / sqrt(max(r_3_2 * (r_3_2 - 3.0 * m * sqrt(r) + 2.0 * a * sqrt_m), 1e-6));
+    return 1.0 / (u_t * (1.0 - omega * p_phi));
+}
+
+// colour of the disk where the photon hit it, in the disk frame
+fn disk_col(photon: Photon) -> vec3<f32> {
+    let m = black_hole_mass();
+    var r: f32;
+    if u32_to_bool(u.KERR) {
+        r = kerr_r(photon.ro, kerr_a());
+    } else {
+        r = length(photon.ro.xz);
+    }
+    let flux = u.ACCRETION_RATE * novikov_thorne_flux(r / m, disk_inner_r() / m, abs(disk_spin()));
+    var brightness = DISK_BRIGHTNESS * flux / DISK_REFERENCE_FLUX;
+    var temperature = disk_temperature(flux);
+    if u32_to_bool(u.REDSHIFT) {
+        let g = disk_redshift(photon, r, m, disk_spin());
+        // a redshifted blackbody is still a blackbody, just at g times the temperature
+        temperature *= g;
+        // I_nu / nu^3 is the same for every observer,
+        // so the total intensity goes as g^4 and the intensity in a fixed band as g^3
+        brightness *= pow(g, u.BEAMING_EXPONENT);
+    }
+    return brightness * blackbody_col(temperature);
+}
+
+// volumetric accretion disk
+// instead of a hit on the thin disk returning its colour,
+// light is picked up and absorbed all the way through a cloud of gas
+
+// the disk gets thicker further out, h is a fraction of the radius
+fn disk_scale_height(radius: f32) -> f32 {
+    return max(u.DISK_SCALE_HEIGHT, 1e-3) * radius;
+}
+
+// bounds the gas, a cylinder with a cone shaped hole in the top and bottom
+fn sdf_disk_volume(p: vec3<f32>) -> f32 {
+    let slope = VOLUME_HEIGHT * max(u.DISK_SCALE_HEIGHT, 1e-3);
+    let sd_height = (abs(p.y) - slope * length(p.xz)) / sqrt(1.0 + slope * slope);
+    let sd_rings = max(sdf_cylinder(p, vec2<f32>(0.0), u.DISK_OUTER_RADIUS * u.RS), -sdf_cylinder(p, vec2<f32>(0.0), disk_inner_radius()));
+    return max(sd_rings, sd_height);
+}
+
+// nothing further than this from the black hole can be hit, the disk and the markers are inside it
+fn surfaces_radius() -> f32 {
+    var disk_radius = u.DISK_OUTER_RADIUS * u.RS;
+    if u32_to_bool(u.VOLUMETRIC) {
+        // it's thickest at the outer edge
+        let slope = VOLUME_HEIGHT * max(u.DISK_SCALE_HEIGHT, 1e-3);
+        disk_radius *= sqrt(1.0 + slope * slope);
+    }
+    return max(disk_radius, MARKERS_RADIUS) + MIN_DIST;
+}
+
+// pcg hash
+fn hash_u32(x: u32) -> u32 {
+    let state = x * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// random number from 0 to 1 for each integer point
+fn hash3(p: vec3<i32>) -> f32 {
+    let h = hash_u32(bitcast<u32>(p.x) ^ hash_u32(bitcast<u32>(p.y) ^ hash_u32(bitcast<u32>(p.z))));
+    return f32(h) / 4294967295.0;
+}
+
+fn value_noise(p: vec3<f32>) -> f32 {
+    let i = vec3<i32>(floor(p));
+    let f = fract(p);
+    let s = f * f * (3.0 - 2.0 * f);
+    let x00 = mix(hash3(i), hash3(i + vec3<i32>(1, 0, 0)), s.x);
+    let x10 = mix(hash3(i + vec3<i32>(0, 1, 0)), hash3(i + vec3<i32>(1, 1, 0)), s.x);
+    let x01 = mix(hash3(i + vec3<i32>(0, 0, 1)), hash3(i + vec3<i32>(1, 0, 1)), s.x);
+    let x11 = mix(hash3(i + vec3<i32>(0, 1, 1)), hash3(i + vec3<i32>(1, 1, 1)), s.x);
+    return mix(mix(x00, x10, s.y), mix(x01, x11, s.y), s.z);
+}
+
+// 0 to 1
+fn fbm(p: vec3<f32>) -> f32 {
+    var total = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0; i < 4; i++) {
+        total += amplitude * value_noise(q);
+        q *= 2.0;
+        amplitude *= 0.5;
+    }
+    return total / 0.9375;
+}
+
+fn disk_density(p: vec3<f32>) -> f32 {
+    let radius = length(p.xz);
+    let inner = disk_inner_radius();
+    let outer = u.DISK_OUTER_RADIUS * u.RS;
+    if radius < inner || radius > outer {
+        return 0.0;
+    }
+    let h = disk_scale_height(radius);
+    // gaussian in height, like gas held up by its own pressure
+    var density = exp(-0.5 * p.y * p.y / (h * h));
+    // fade out the outer edge instead of cutting it off
+    density *= 1.0 - smoothstep(0.8 * outer, outer, radius);
+    // the turbulence is carried around at the orbital speed of each ring
+    // so the inside moves faster than the outside and it gets sheared into spirals
+    let angle = -keplerian_omega(radius, black_hole_mass(), disk_spin()) * DISK_TIME_SCALE * camera.time;
+    let q = rotate_vector(p, vec3<f32>(0.0, 1.0, 0.0), angle);
+    let noise = fbm(2.0 * q / u.RS);
+    density *= max(1.0 + u.DISK_TURBULENCE * (2.0 * noise - 1.0), 0.0);
+    return density;
+}
+
+struct Volume {
+    // light picked up so far
+    col: vec3<f32>,
+    // how much of whatever is behind gets through
+    transmittance: f32,
+}
+
+// light picked up going ds through the disk at the photon
+// the gas is in thermal equilibrium, so it emits the same blackbody as it absorbs (kirchhoff)
+// and the parts too thick to see through look like the thin disk
+fn march_volume(volume: Volume, photon: Photon, ds: f32) -> Volume {
+    let absorption = u.DISK_OPACITY * disk_density(photon.ro);
+    if absorption <= 0.0 {
+        return volume;
+    }
+    let step_transmittance = exp(-absorption * ds);
+    let col = volume.col + volume.transmittance * (1.0 - step_transmittance) * disk_col(photon);
+    return Volume(col, volume.transmittance * step_transmittance);
 }
 
 fn tsw(t: texture_2d<f32>, s: sampler, p: vec2f) -> vec4f {
@@ -256,47 +473,402 @@ fn ray_march_photon(photon: Photon) -> vec3f {
     }
 }
 */
+// has to match the constants in deflection_lut.rs
+const DEFLECTION_LUT_MIN_RADIUS = 1.0;
+const DEFLECTION_LUT_MAX_RADIUS = 1000.0;
+const ORBIT_TABLE_MIN_PERIAPSIS_GAP = 1e-3;
+const ORBIT_TABLE_MIN_GAP = 1e-6;
+const ORBIT_TABLE_MAX_GAP = 1e6;
+const ORBIT_TABLE_MAX_X = 4.0;
+// orbits with this c only get as far in as the photon sphere
+const ORBIT_CRITICAL_C = 0.148148148;
+
+// bilinear, texel is in texels with whole numbers at the centres
+// float32 textures can't be filtered everywhere so it's done here
+fn load_bilinear(t: texture_2d<f32>, texel: vec2f) -> vec4f {
+    let size = vec2<i32>(textureDimensions(t));
+    let clamped = clamp(texel, vec2f(0.0), vec2f(size - 1));
+    let low = vec2<i32>(floor(clamped));
+    let high = min(low + 1, size - 1);
+    let f = clamped - vec2f(low);
+    let a = textureLoad(t, low, 0);
+    let b = textureLoad(t, vec2<i32>(high.x, low.y), 0);
+    let c = textureLoad(t, vec2<i32>(low.x, high.y), 0);
+    let d = textureLoad(t, high, 0);
+    return mix(mix(a, b, f.x), mix(c, d, f.x), f.y);
+}
+
+fn deflection_lut_load(texel: vec2f) -> vec4f {
+    return load_bilinear(deflection_lut, texel);
+}
+
+fn orbit_table_load(texel: vec2f) -> vec4f {
+    return load_bilinear(orbit_table, texel);
+}
+
+// get_col for a non spinning black hole without the disk
+// the photon stays in the plane of its direction and the black hole, and the table has how far
+// it turns in that plane for the distance it starts at and its angle from the black hole
+fn get_col_from_lut(photon: Photon) -> vec3<f32> {
+    let size = vec2f(textureDimensions(deflection_lut));
+    let radius = length(photon.ro);
+    let inward = -photon.ro / radius;
+    let angle = acos(clamp(dot(photon.rd, inward), -1.0, 1.0));
+
+    // the rows go up exponentially
+    let row = log(radius / DEFLECTION_LUT_MIN_RADIUS)
+        / log(DEFLECTION_LUT_MAX_RADIUS / DEFLECTION_LUT_MIN_RADIUS)
+        * (size.y - 1.0);
+    // every texel in a row has the edge of the shadow, the columns start at it
+    let edge = deflection_lut_load(vec2f(0.0, row)).z;
+    if angle < edge {
+        return vec3<f32>(0.0);
+    }
+    // and get closer together towards it
+    let column = sqrt((angle - edge) / max(ONE_PI - edge, 1e-6)) * (size.x - 1.0);
+    let texel = deflection_lut_load(vec2f(column, row));
+
+    // straight at or away from the black hole, any plane through it works
+    var axis = cross(inward, photon.rd);
+    if dot(axis, axis) < 1e-12 {
+        axis = cross(inward, select(vec3f(1.0, 0.0, 0.0), vec3f(0.0, 1.0, 0.0), abs(inward.x) > 0.9));
+    }
+    axis = normalize(axis);
+    let turn = normalize(texel.xy);
+    let final_rd = photon.rd * turn.x + cross(axis, photon.rd) * turn.y;
+    // w is how much of the texel escaped
+    return texel.w * background_col(final_rd);
+}
+
+// an orbit around a non spinning black hole from the orbit table, see deflection_lut.rs
+// with x = k / r, x goes from 0 at infinity up to x_end at angle_end,
+// and back out again if it isn't captured
+struct TableOrbit {
+    row: f32,
+    c: f32,
+    angle_end: f32,
+    x_end: f32,
+    captured: bool,
+}
+
+fn table_orbit(c: f32) -> TableOrbit {
+    let rows = f32(textureDimensions(orbit_table).y / 2u);
+    let captured = c > ORBIT_CRITICAL_C;
+    // the rows get exponentially further from the critical orbit on either side of it
+    // and they're kept on their own side, so captured orbits aren't mixed with ones that aren't
+    var row: f32;
+    if captured {
+        let gap = max(c / ORBIT_CRITICAL_C - 1.0, ORBIT_TABLE_MIN_GAP);
+        row = rows + clamp(log(gap / ORBIT_TABLE_MIN_GAP) / log(ORBIT_TABLE_MAX_GAP / ORBIT_TABLE_MIN_GAP), 0.0, 1.0) * (rows - 1.0);
+    } else {
+        // the middle root of x^3 - x^2 + c
+        let periapsis = 1.0 / 3.0 + 2.0 / 3.0 * cos((acos(clamp(1.0 - 13.5 * c, -1.0, 1.0)) - TWO_PI) / 3.0);
+        let gap = max(1.0 - 1.5 * periapsis, ORBIT_TABLE_MIN_PERIAPSIS_GAP);
+        row = clamp(1.0 - log(gap) / log(ORBIT_TABLE_MIN_PERIAPSIS_GAP), 0.0, 1.0) * (rows - 1.0);
+    }
+    let ends = orbit_table_load(vec2f(0.0, row));
+    return TableOrbit(row, c, ends.z, ends.w, captured);
+}
+
+// x at an angle from where the orbit came in from infinity
+// past angle_end it goes back out the same way, and before 0 it's the same as after
+fn orbit_x(orbit: TableOrbit, angle: f32) -> f32 {
+    var from_infinity = abs(angle);
+    if !orbit.captured {
+        from_infinity = min(from_infinity, 2.0 * orbit.angle_end - from_infinity);
+    }
+    let columns = f32(textureDimensions(orbit_table).x);
+    let column = from_infinity / orbit.angle_end * (columns - 1.0);
+    return orbit.x_end * orbit_table_load(vec2f(column, orbit.row)).x;
+}
+
+// the angle from infinity where the orbit gets to x on the way in
+// the columns get closer together towards x_end, where the angle changes fastest
+fn orbit_angle(orbit: TableOrbit, x: f32) -> f32 {
+    let columns = f32(textureDimensions(orbit_table).x);
+    let column = (1.0 - sqrt(max(1.0 - x / orbit.x_end, 0.0))) * (columns - 1.0);
+    return orbit.angle_end * orbit_table_load(vec2f(column, orbit.row)).y;
+}
+
+// how fast x changes with the angle, from (x')^2 + x^2 - x^3 = c
+// it goes up on the way in and down on the way back out
+fn orbit_x_rate(orbit: TableOrbit, angle: f32, x: f32) -> f32 {
+    let rate = sqrt(max(orbit.c - x * x + x * x * x, 0.0));
+    if angle >= 0.0 && angle < orbit.angle_end {
+        return rate;
+    }
+    return -rate;
+}
+
+struct TableCol {
+    col: vec3<f32>,
+    // false if the tables can't do this ray and it has to be marched
+    valid: bool,
+}
+
+// get_col for a non spinning black hole without marching, photon is in the disk frame
+// the orbit table has where the ray is at every angle around its plane, which is enough to find
+// where it crosses the plane of the disk and passes the markers,
+// and the deflection table has where it goes after that
+// the volumetric disk still has to be marched through, unless the ray never gets near it
+fn get_col_from_tables(photon: Photon) -> TableCol {
+    let invalid = TableCol(vec3<f32>(0.0), false);
+    let k = u.DISTORTION_POWER * u.RS;
+    let r0 = length(photon.ro);
+    let x0 = k / r0;
+    let l = cross(photon.ro, photon.rd);
+    let h = length(l);
+    // without the horizon rays go through the middle, and the table stops at ORBIT_TABLE_MAX_X
+    // close to the photon sphere f32 isn't good enough for where along the orbit the camera is
+    // and straight at or away from the black hole there's no plane
+    if !u32_to_bool(u.BLACKOUT_EH) || k <= 0.0 || k >= ORBIT_TABLE_MAX_X || r0 <= 1.0 || x0 > 0.6 || h < 1e-4 * r0 {
+        return invalid;
+    }
+    // a photon with a unit rd has (x')^2 + x^2 = k^2 / h^2 wherever it starts
+    let c = k * k / (h * h) - x0 * x0 * x0;
+    if c > ORBIT_CRITICAL_C * (1.0 + ORBIT_TABLE_MAX_GAP) {
+        return invalid;
+    }
+    let orbit = table_orbit(c);
+
+    // the angles along the orbit that the ray goes between
+    // it ends at the horizon, r = 1, or at infinity
+    let outward = dot(photon.ro, photon.rd) > 0.0;
+    let horizon_x = k;
+    let falls_in = !outward && (orbit.captured || orbit.x_end >= horizon_x);
+    var start = orbit_angle(orbit, x0);
+    var end: f32;
+    if falls_in {
+        end = orbit_angle(orbit, horizon_x);
+    } else if outward && (orbit.captured || orbit.x_end >= horizon_x) {
+        // came out from inside the horizon, so going forwards it's like going back in
+        start = -start;
+        end = 0.0;
+    } else {
+        if outward {
+            start = 2.0 * orbit.angle_end - start;
+        }
+        end = 2.0 * orbit.angle_end;
+    }
+    let sweep = end - start;
+
+    // the lookup table on its own is enough if it never gets as close as anything it could hit
+    var closest_x = x0;
+    if !outward {
+        closest_x = min(orbit.x_end, horizon_x);
+    }
+    let world_photon = Photon(from_disk_frame(photon.ro), from_disk_frame(photon.rd));
+    if closest_x * surfaces_radius() < k {
+        return TableCol(get_col_from_lut(world_photon), true);
+    }
+    if u32_to_bool(u.VOLUMETRIC) {
+        return invalid;
+    }
+
+    // the plane of the orbit, it starts at e1 and turns towards e2
+    let n = l / h;
+    // in the plane of the disk the ray goes along it rather than crossing it
+    if abs(n.y) > 0.9999 {
+        return invalid;
+    }
+    let e1 = photon.ro / r0;
+    let e2 = cross(n, e1);
+
+    // the first thing it hits, as how far it's turned
+    var hit = sweep;
+    var col = vec3<f32>(0.0);
+
+    // it crosses the plane of the disk every half turn
+    var crossing = atan2(-e1.y, e2.y);
+    if crossing < 0.0 {
+        crossing += ONE_PI;
+    }
+    let inner_x = k / disk_inner_radius();
+    let outer_x = k / (u.DISK_OUTER_RADIUS * u.RS);
+    for (; crossing < sweep; crossing += ONE_PI) {
+        let x = orbit_x(orbit, start + crossing);
+        if x >= outer_x && x <= inner_x {
+            let p = k / x * (cos(crossing) * e1 + sin(crossing) * e2);
+            // disk_col only needs the angular momentum, which doesn't change along the ray
+            hit = crossing;
+            col = disk_col(Photon(p, cross(l, p) / dot(p, p)));
+            break;
+        }
+    }
+
+    // the markers are small enough that the ray is close to straight while it goes past one
+    var markers = array<vec3<f32>, 4>(
+        vec3<f32>(0.0, 10.0, -10.0),
+        vec3<f32>(0.0, -10.0, -10.0),
+        vec3<f32>(10.0, 0.0, -10.0),
+        vec3<f32>(-10.0, 0.0, -10.0),
+    );
+    for (var i = 0; i < 4; i++) {
+        let centre = to_disk_frame(markers[i]);
+        let height = dot(centre, n);
+        if abs(height) >= 0.5 {
+            continue;
+        }
+        // where it cuts the plane of the orbit
+        let circle_radius = sqrt(0.25 - height * height);
+        let circle_centre = centre - height * n;
+        var passing = atan2(dot(circle_centre, e2), dot(circle_centre, e1));
+        if passing < 0.0 {
+            passing += TWO_PI;
+        }
+        for (; passing < hit; passing += TWO_PI) {
+            let angle = start + passing;
+            let x = orbit_x(orbit, angle);
+            let r = k / x;
+            let radial = cos(passing) * e1 + sin(passing) * e2;
+            let around = cross(n, radial);
+            // dr / dangle = -k x' / x^2
+            let tangent = normalize(-k * orbit_x_rate(orbit, angle, x) / (x * x) * radial + r * around);
+            let to_centre = circle_centre - r * radial;
+            let along = dot(to_centre, tangent);
+            if dot(to_centre, to_centre) - along * along < circle_radius * circle_radius {
+                hit = passing;
+                col = vec3<f32>(1.0);
+                break;
+            }
+        }
+    }
+
+    if hit < sweep {
+        return TableCol(col, true);
+    }
+    // it didn't hit anything, so it ends up where the ray from the camera does
+    return TableCol(get_col_from_lut(world_photon), true);
+}
+
+// the background in the given direction, in the world frame
+fn background_col(rd: vec3f) -> vec3<f32> {
+    // any unit vector
+    let normalized_final_rd = normalize(rd);
+    // range -PI to +PI
+    let azimuthal_angle = atan2(normalized_final_rd.z, normalized_final_rd.x);
+    // range 0 to 1
+    let x = (azimuthal_angle + ONE_PI) / TWO_PI;
+    // range 0 to 1
+    let y = (normalized_final_rd.y + 1.0) * 0.5;
+
+    // 1 - y because in texture coords, +y is down
+    // let col = tsw(t_diffuse, s_diffuse, vec2<f32>(x, 1.0 - y)).xyz;
+    // let col = textureSampleLevel(t_diffuse, s_diffuse, vec2<f32>(floor(x), floor(1.0 - y)), 0.0).xyz;
+    var col = textureSampleLevel(t_diffuse, s_diffuse, vec2<f32>(x, 1.0 - y), 0.0).xyz;
+    col.y = pow(col.y, 1.5);
+    col.z = pow(col.z, 1.5);
+    return col;
+}
+
 fn get_col(initial_photon: Photon) -> vec3<f32> {
-    var photon = Photon(initial_photon.ro, initial_photon.rd); 
+    // the lookup tables only know about non spinning black holes
+    // rays they can't do are still marched until they can't hit anything and then looked up
+    let lut = u32_to_bool(u.DEFLECTION_LUT) && !u32_to_bool(u.KERR);
+    let lut_radius = surfaces_radius();
+
+    // everything is integrated in the frame of the disk
+    let initial_ro = to_disk_frame(initial_photon.ro);
+    var photon = Photon(initial_ro, to_disk_frame(initial_photon.rd));
+
+    if lut {
+        let table = get_col_from_tables(Photon(initial_ro, normalize(photon.rd)));
+        if table.valid {
+            return table.col;
+        }
+    }
 
     let initial_ro_rd_cross = cross(photon.ro, photon.rd);
     let h2 = dot(initial_ro_rd_cross, initial_ro_rd_cross);
+
+    let kerr = u32_to_bool(u.KERR);
+    let kerr_m = black_hole_mass();
+    let kerr_spin_a = kerr_trace_a();
+    let kerr_r_horizon = kerr_horizon_radius();
+    if kerr {
+        photon.rd = kerr_initial_momentum(photon.ro, photon.rd, kerr_m, kerr_spin_a);
+    }
+
+    let adaptive = u.INTEGRATOR == INTEGRATOR_RK45;
+    // kerr isn't spherically symmetric, and the plane isn't defined for rays straight through the middle
+    // rk4 does those
+    let binet = u.INTEGRATOR == INTEGRATOR_BINET && !kerr && h2 > 1e-12;
+    var orbit: Orbit;
+    if binet {
+        orbit = orbit_from_photon(photon, h2);
+    }
+    let tolerance = pow(10.0, u.LOG_TOLERANCE);
+    // the adaptive integrator starts off with the same step as rk4 and then finds its own
+    var adaptive_delta_time = DELTA_TIME_MULT * length(photon.ro);
+    var k1 = photon_derivative(photon, h2, kerr, kerr_m, kerr_spin_a);
+
+    let volumetric = u32_to_bool(u.VOLUMETRIC);
+    var volume = Volume(vec3<f32>(0.0), 1.0);
+
     var distance_travelled = 0.0;
     var has_been_outside_eh = false;
     for (var i = 0; i < MAX_ITERATIONS; i++) {
+        // nothing behind this can be seen
+        if volume.transmittance < 0.01 {
+            return volume.col;
+        }
+
         // the photon should approach the singularity
         // given the desired distance calculation
         // just like the ray approaches a surface in raymarching
         // there also be some distance to the singularity that will cause black
         let dist_to_singularity = length(photon.ro);
-        if u32_to_bool(u.BLACKOUT_EH) {
+        if kerr {
+            // kerr-schild coordinates go straight through the horizon
+            // so anything that gets inside it is gone
+            if kerr_r(photon.ro, kerr_spin_a) < kerr_r_horizon {
+                return volume.col;
+            }
+        } else if u32_to_bool(u.BLACKOUT_EH) {
             if dist_to_singularity < 1.0 {
                 if dot(photon.rd, photon.ro) < 0.0 {
-                    return vec3<f32>(0.0);
+                    return volume.col;
                 }
             }
             if dist_to_singularity > 1.0 {
                 has_been_outside_eh = true;
             } else if has_been_outside_eh {
-                return vec3<f32>(0.0);
+                return volume.col;
             }
         }
 
-        let dist_to_surfaces = sdf(photon.ro);
-        if dist_to_surfaces < MIN_DIST {
-            return vec3<f32>(1.0);
+        // past its closest approach and outside everything, so it only gets further away from here
+        // and the table can take it the rest of the way
+        if lut && dist_to_singularity > lut_radius && dot(photon.ro, photon.rd) > 0.0 {
+            let world_photon = Photon(from_disk_frame(photon.ro), normalize(from_disk_frame(photon.rd)));
+            return volume.col + volume.transmittance * get_col_from_lut(world_photon);
         }
 
-        // photon is a small sphere at the back of the black hole
-        // distance of 1.5 * r_s away
-        // we say that if a photon hits this sphere, it goes into temporary orbit around the black hole
-        // https://upload.wikimedia.org/wikipedia/commons/2/27/Black_Hole_Shadow.gif
-        let photon_sphere_dist = sdf_sphere(photon.ro, -normalize(initial_photon.ro) * 1.5 * u.RS, 0.075);
-        //if photon_sphere_dist < MIN_DIST {
-        //    return vec3<f32>(1.0, 1.0, 0.0);
-        //}
-
-        let dist = min(dist_to_surfaces, photon_sphere_dist);
+        var dist_to_disk: f32;
+        var in_volume = false;
+        if volumetric {
+            // the photon can't stop at the disk, it has to go through in steps small enough to see the gas
+            // and it has to go in, not just get closer and closer to the edge
+            let volume_step = max(VOLUME_STEP * disk_scale_height(length(photon.ro.xz)), 10.0 * MIN_DIST);
+            dist_to_disk = sdf_disk_volume(photon.ro);
+            if dist_to_disk < volume_step {
+                in_volume = true;
+                dist_to_disk = volume_step;
+            }
+        } else {
+            dist_to_disk = sdf_disk(photon.ro);
+            if dist_to_disk < MIN_DIST {
+                return disk_col(photon);
+            }
+        }
+        let dist_to_markers = sdf_markers(from_disk_frame(photon.ro));
+        if dist_to_markers < MIN_DIST {
+            return volume.col + volume.transmittance * vec3<f32>(1.0);
+        }
+        // there used to be a small sphere on the photon sphere behind the black hole here too
+        // but with nothing to hit on it, photons crept up to it in smaller and smaller steps,
+        // ran out of iterations and drew a ring of background inside the shadow
+        let dist = min(dist_to_disk, dist_to_markers);
 
         // the photon should be able to travel further if it is far away from the black hole
         // k * distance to event horizon
@@ -304,13 +876,61 @@ fn get_col(initial_photon: Photon) -> vec3<f32> {
         // also, when travelling away from the black hole,
         // the distance away from the black hole should grow exponentially
         // this means max view distance can be increased massively
-        var dd = u.DELTA_TIME_MULT * dist_to_singularity;
+        var dd = DELTA_TIME_MULT * dist_to_singularity;
         // then apply the ray marching distance
         // 0.9 multiplier just to account for any error due to the curvature of the ray
         dd = min(dist * 0.9, dd);
 
+        if binet {
+            if in_volume {
+                volume = march_volume(volume, photon, dd);
+            }
+            orbit = step_orbit(orbit, orbit_delta_phi(orbit, dd), kerr_m);
+            photon = orbit_photon(orbit);
+            distance_travelled += dd;
+            if distance_travelled > u.MAX_DIST || orbit.u <= 0.0 {
+                break;
+            }
+            continue;
+        }
+
+        if adaptive {
+            // the step size comes from the error estimate of the last step
+            // but it still can't go past the nearest surface or jump over the black hole
+            let speed = max(length(k1.ro), 1e-6);
+            let delta_time = min(adaptive_delta_time, dd / speed);
+            let step = get_delta_photon_rk45(photon, k1, delta_time, tolerance, h2, kerr, kerr_m, kerr_spin_a);
+            // standard step size control, with the growth and shrinkage limited
+            let step_scale = 0.9 * pow(max(step.error_ratio, 1e-10), -0.2);
+            adaptive_delta_time = delta_time * clamp(step_scale, 0.2, 5.0);
+            if step.error_ratio > 1.0 {
+                // rejected, try again from the same place with the smaller step
+                continue;
+            }
+            if in_volume {
+                volume = march_volume(volume, photon, delta_time * speed);
+            }
+            photon.ro += step.delta.ro;
+            photon.rd += step.delta.rd;
+            k1 = step.k_end;
+            distance_travelled += delta_time * speed;
+            if distance_travelled > u.MAX_DIST {
+                break;
+            }
+            continue;
+        }
+
         // how the photon should move given the desired distance and the current state of the photonn
-        let delta_photon = get_delta_photon_rk4(photon, dd, h2);
+        var delta_photon: Photon;
+        if kerr {
+            delta_photon = get_delta_photon_kerr_rk4(photon, dd, kerr_m, kerr_spin_a);
+        } else {
+            delta_photon = get_delta_photon_rk4(photon, dd, h2);
+        }
+
+        if in_volume {
+            volume = march_volume(volume, photon, dd);
+        }
 
         photon.ro += delta_photon.ro;
         // photon.rd won't be a unit vector at all points in the loop
@@ -326,32 +946,12 @@ fn get_col(initial_photon: Photon) -> vec3<f32> {
             break;
         }
     }
-    // any unit vector
-    let normalized_final_rd = normalize(photon.rd);
-    // range -PI to +PI
-    let azimuthal_angle = atan2(normalized_final_rd.z, normalized_final_rd.x);
-    // range 0 to 1
-    let x = (azimuthal_angle + ONE_PI) / TWO_PI;
-    // range 0 to 1
-    let y = (normalized_final_rd.y + 1.0) * 0.5;
-
-    // 1 - y because in texture coords, +y is down
-    // let col = tsw(t_diffuse, s_diffuse, vec2<f32>(x, 1.0 - y)).xyz;
-    // let col = textureSampleLevel(t_diffuse, s_diffuse, vec2<f32>(floor(x), floor(1.0 - y)), 0.0).xyz;
-    var col = textureSampleLevel(t_diffuse, s_diffuse, vec2<f32>(x, 1.0 - y), 0.0).xyz;
-    col.y = pow(col.y, 1.5);
-    col.z = pow(col.z, 1.5);
-    return col;
-}
-
-fn map_col_component_infinity_to_one(component: f32) -> f32 {
-    return 1.0 - 1.0 / (component + 1.0);
-}
-
-fn map_col_infinity_to_one(col: vec3<f32>) -> vec3<f32> {
-    return vec3<f32>(map_col_component_infinity_to_one(col.x), map_col_component_infinity_to_one(col.y), map_col_component_infinity_to_one(col.z));
+    // back in the world frame
+    let col = background_col(from_disk_frame(photon.rd));
+    return volume.col + volume.transmittance * col;
 }
 
+#ifndef COMPUTE
 struct FragmentOutput {
     @location(0) col: vec4<f32>,
     @location(1) blackout_col: vec4<f32>,
@@ -362,9 +962,7 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     let ray_dir = normalize(in.camera_to_vertex);
     let photon = Photon(camera.pos.xyz, ray_dir);
     let col = get_col(photon);
-    var blackout_col = col;
-    if dot(col, col) < 1.0 {
-        blackout_col = vec3f(0.0);
-    }
-    return FragmentOutput(vec4<f32>(col, 1.0), vec4<f32>(blackout_col, 1.0));
+    // the bright pass is done by the first copy in the bloom chain, so this gets everything
+    return FragmentOutput(vec4<f32>(col, 1.0), vec4<f32>(col, 1.0));
 }
+#endif
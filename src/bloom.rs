@@ -1,72 +1,284 @@
-use crate::{blur::Blur, copy::Copy, remix::Remix};
+use std::sync::Arc;
 
-pub struct Bloom {
-    pub blurs: Vec<Blur>,
-    pub copies: Vec<Copy>,
-    pub remixes: Vec<Remix>,
-    pub final_remix: Remix,
-    levels: usize,
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{
+    blur::add_blur,
+    fullscreen_pass::FullscreenPass,
+    otheruniforms::BufferContent,
+    render_graph::{RenderGraph, Target, TextureHandle, TextureSize},
+    settings::number_from_virtual_key_code,
+};
+
+// what a level's blur gets mixed in with if it isn't given a weight
+pub const DEFAULT_LEVEL_WEIGHT: f32 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomSettings {
+    // each level blurs the one before it over one more kawase level, at least 1
+    pub levels: usize,
+    // colours with a length below this don't bloom
+    pub threshold: f32,
+    // how much of the bloom gets added onto the image
+    pub intensity: f32,
+    // how far apart the kawase samples are, in half pixels
+    pub radius: f32,
+    // how much of each level's blur gets added back onto it before the next level
+    // levels past the end of this use DEFAULT_LEVEL_WEIGHT
+    pub level_weights: Vec<f32>,
 }
 
-impl Bloom {
-    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, levels: usize) -> Self {
-        let mut blurs = Vec::new();
-        let mut copies = Vec::new();
-        let mut remixes = Vec::new();
-        for level in 1..=levels {
-            blurs.push(Blur::new(device, config, level));
-            copies.push(Copy::new(device, config));
-            remixes.push(Remix::new(device, config));
-        }
-        let final_remix = Remix::new(device, config);
+impl Default for BloomSettings {
+    fn default() -> Self {
         Self {
-            blurs,
-            copies,
-            remixes,
-            final_remix,
-            levels,
+            levels: 3,
+            threshold: 1.0,
+            intensity: 0.5,
+            radius: 3.0,
+            level_weights: vec![DEFAULT_LEVEL_WEIGHT; 3],
         }
     }
+}
 
-    pub fn full_image_input_texture_view(&self) -> &wgpu::TextureView {
-        &self.final_remix.input_texture_0_view()
+impl BloomSettings {
+    pub fn level_weight(&self, level: usize) -> f32 {
+        self.level_weights
+            .get(level)
+            .copied()
+            .unwrap_or(DEFAULT_LEVEL_WEIGHT)
     }
+}
 
-    pub fn blackout_input_texture_view(&self) -> &wgpu::TextureView {
-        &self.copies[0].input_texture_view()
+// the uniforms live here and are shared with the passes bloom declares in the render graph
+// so changing a setting doesn't need the graph, apart from the level count
+pub struct Bloom {
+    settings: BloomSettings,
+    // the level count add_to_graph last declared
+    declared_levels: usize,
+
+    pub threshold_uniform_buffer: Arc<wgpu::Buffer>,
+    pub intensity_uniform_buffer: Arc<wgpu::Buffer>,
+    pub radius_uniform_buffer: Arc<wgpu::Buffer>,
+    // one per declared level
+    pub level_weight_uniform_buffers: Vec<Arc<wgpu::Buffer>>,
+
+    // while b is held the number keys set the level count
+    levels_modifier_pressed: bool,
+}
+
+fn create_uniform_buffer(device: &wgpu::Device, label: &str, value: f32) -> Arc<wgpu::Buffer> {
+    Arc::new(
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: &value.uniform_buffer_content(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        }),
+    )
+}
+
+impl Bloom {
+    pub fn new(device: &wgpu::Device, mut settings: BloomSettings) -> Self {
+        settings.levels = settings.levels.max(1);
+        Self {
+            threshold_uniform_buffer: create_uniform_buffer(
+                device,
+                "bloom threshold uniform buffer",
+                settings.threshold,
+            ),
+            intensity_uniform_buffer: create_uniform_buffer(
+                device,
+                "bloom intensity uniform buffer",
+                settings.intensity,
+            ),
+            radius_uniform_buffer: create_uniform_buffer(
+                device,
+                "bloom radius uniform buffer",
+                settings.radius,
+            ),
+            level_weight_uniform_buffers: vec![],
+            declared_levels: 0,
+            settings,
+
+            levels_modifier_pressed: false,
+        }
     }
 
-    pub fn resize(
+    // declares the bright pass, then for each level a blur of the level before it that gets
+    // mixed back onto it, and finally the last level added onto image
+    // returns the texture with image and its bloom
+    pub fn add_to_graph(
         &mut self,
+        graph: &mut RenderGraph,
         device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
-        queue: &wgpu::Queue,
-    ) {
-        for blur in &mut self.blurs {
-            blur.resize(device, config, queue);
-        }
-        for remix in &mut self.remixes {
-            remix.resize(device, config);
+        image: TextureHandle,
+        bright_source: TextureHandle,
+    ) -> TextureHandle {
+        let format = graph.format(image);
+        let levels = self.settings.levels;
+
+        let bright = graph.add_texture("bloom bright pass texture", TextureSize::Full, format);
+        graph.add_pass(
+            "bloom bright pass",
+            &[bright_source],
+            &[Target::Texture(bright)],
+            FullscreenPass::new(
+                device,
+                "bloom bright pass",
+                include_shader!("src/copy.wgsl"),
+                1,
+                vec![self.threshold_uniform_buffer.clone()],
+                format,
+            ),
+        );
+
+        self.level_weight_uniform_buffers.clear();
+        let mut level_input = bright;
+        for level in 0..levels {
+            let weight_uniform_buffer = create_uniform_buffer(
+                device,
+                &format!("bloom level {} weight uniform buffer", level),
+                self.settings.level_weight(level),
+            );
+            let blurred = graph.add_texture(
+                &format!("bloom level {} blurred texture", level),
+                TextureSize::Full,
+                format,
+            );
+            add_blur(
+                graph,
+                device,
+                &format!("bloom level {}", level),
+                level_input,
+                blurred,
+                level + 1,
+                &self.radius_uniform_buffer,
+            );
+            let level_output = graph.add_texture(
+                &format!("bloom level {} texture", level),
+                TextureSize::Full,
+                format,
+            );
+            graph.add_pass(
+                &format!("bloom level {} remix", level),
+                &[level_input, blurred],
+                &[Target::Texture(level_output)],
+                FullscreenPass::new(
+                    device,
+                    &format!("bloom level {} remix", level),
+                    include_shader!("src/remix.wgsl"),
+                    2,
+                    vec![weight_uniform_buffer.clone()],
+                    format,
+                ),
+            );
+            self.level_weight_uniform_buffers
+                .push(weight_uniform_buffer);
+            level_input = level_output;
         }
+
+        let output = graph.add_texture("bloom output texture", TextureSize::Full, format);
+        graph.add_pass(
+            "bloom final remix",
+            &[image, level_input],
+            &[Target::Texture(output)],
+            FullscreenPass::new(
+                device,
+                "bloom final remix",
+                include_shader!("src/remix.wgsl"),
+                2,
+                vec![self.intensity_uniform_buffer.clone()],
+                format,
+            ),
+        );
+        self.declared_levels = levels;
+        output
     }
 
-    pub fn render(
-        &self,
-        encoder: &mut wgpu::CommandEncoder,
-        output_view: Option<&wgpu::TextureView>,
-    ) {
-        for level in 0..self.levels - 1 {
-            self.copies[0].render(encoder, Some(self.blurs[0].input_texture_view()));
-            self.copies[0].render(encoder, Some(self.remixes[0].input_texture_0_view()));
-            self.blurs[0].render(encoder, Some(self.remixes[0].input_texture_1_view()));
-            self.remixes[0].render(encoder, Some(self.copies[level + 1].input_texture_view()));
+    pub fn settings(&self) -> &BloomSettings {
+        &self.settings
+    }
+
+    // whether the level count changed since the graph was declared
+    pub fn needs_rebuild(&self) -> bool {
+        self.settings.levels != self.declared_levels
+    }
+
+    // everything but the level count only rewrites uniforms
+    // changing the level count needs the graph rebuilding, see needs_rebuild
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, mut settings: BloomSettings) {
+        settings.levels = settings.levels.max(1);
+        queue.write_buffer(
+            &self.threshold_uniform_buffer,
+            0,
+            &settings.threshold.uniform_buffer_content(),
+        );
+        queue.write_buffer(
+            &self.intensity_uniform_buffer,
+            0,
+            &settings.intensity.uniform_buffer_content(),
+        );
+        queue.write_buffer(
+            &self.radius_uniform_buffer,
+            0,
+            &settings.radius.uniform_buffer_content(),
+        );
+        for (level, buffer) in self.level_weight_uniform_buffers.iter().enumerate() {
+            queue.write_buffer(
+                buffer,
+                0,
+                &settings.level_weight(level).uniform_buffer_content(),
+            );
         }
+        self.settings = settings;
+    }
 
-        self.copies[self.levels - 1].render(encoder, Some(self.blurs[self.levels - 1].input_texture_view()));
-        self.copies[self.levels - 1].render(encoder, Some(self.remixes[self.levels - 1].input_texture_0_view()));
-        self.blurs[self.levels - 1].render(encoder, Some(self.remixes[self.levels - 1].input_texture_1_view()));
-        self.remixes[self.levels - 1].render(encoder, Some(self.final_remix.input_texture_1_view()));
+    // if this changes the level count the graph has to be rebuilt, see needs_rebuild
+    pub fn process_event(&mut self, event: &WindowEvent, queue: &wgpu::Queue) -> bool {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(code),
+                    state,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return false;
+        };
+        let is_pressed = *state == ElementState::Pressed;
+        if *code == KeyCode::KeyB {
+            self.levels_modifier_pressed = is_pressed;
+            return true;
+        }
+        if !is_pressed {
+            return false;
+        }
 
-        self.final_remix.render(encoder, output_view);
+        let mut settings = self.settings.clone();
+        match code {
+            KeyCode::Minus => settings.intensity = (settings.intensity - 0.1).max(0.0),
+            KeyCode::Equal => settings.intensity += 0.1,
+            KeyCode::Comma => settings.threshold = (settings.threshold - 0.1).max(0.0),
+            KeyCode::Period => settings.threshold += 0.1,
+            KeyCode::Semicolon => settings.radius = (settings.radius - 0.5).max(0.0),
+            KeyCode::Quote => settings.radius += 0.5,
+            _ => match number_from_virtual_key_code(code) {
+                Some(levels) if self.levels_modifier_pressed && levels > 0 => {
+                    settings.levels = levels
+                }
+                _ => return false,
+            },
+        }
+        println!(
+            "bloom levels: {}, threshold: {:.1}, intensity: {:.1}, radius: {:.1}",
+            settings.levels, settings.threshold, settings.intensity, settings.radius
+        );
+        self.set_settings(queue, settings);
+        true
     }
 }
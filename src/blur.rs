@@ -1,46 +1,111 @@
-// use crate::{kawase_downsampling::KawaseDownsampling, kawase_mixing_upsampling::KawaseMixingUpsampling};
+use std::sync::Arc;
 
-use crate::{kawase_downsampling::KawaseDownsampling, kawase_upsampling::KawaseUpsampling};
+use crate::{
+    fullscreen_pass::FullscreenPass,
+    render_graph::{RenderGraph, Target, TextureHandle, TextureSize},
+};
 
-pub struct Blur {
-    pub downsampling: KawaseDownsampling,
-    pub upsampling: KawaseUpsampling,
-    levels: usize,
+fn downsampling(
+    device: &wgpu::Device,
+    label: &str,
+    format: wgpu::TextureFormat,
+    offset_uniform_buffer: &Arc<wgpu::Buffer>,
+) -> FullscreenPass {
+    FullscreenPass::new(
+        device,
+        label,
+        include_shader!("src/kawase_downsample.wgsl"),
+        1,
+        vec![offset_uniform_buffer.clone()],
+        format,
+    )
 }
 
-impl Blur {
-    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, levels: usize) -> Self {
-        let downsampling = KawaseDownsampling::new(device, config, levels);
-        let upsampling = KawaseUpsampling::new(device, config, levels);
+fn upsampling(
+    device: &wgpu::Device,
+    label: &str,
+    format: wgpu::TextureFormat,
+    offset_uniform_buffer: &Arc<wgpu::Buffer>,
+) -> FullscreenPass {
+    FullscreenPass::new(
+        device,
+        label,
+        include_shader!("src/kawase_upsample.wgsl"),
+        1,
+        vec![offset_uniform_buffer.clone()],
+        format,
+    )
+}
 
-        Self {
-            downsampling,
-            upsampling,
-            levels,
-        }
-    }
+// declares a kawase blur of input into output, halving the resolution levels - 1 times
+// on the way down and doubling it back up
+// offset_uniform_buffer is how far apart the kawase samples are, in half pixels
+pub fn add_blur(
+    graph: &mut RenderGraph,
+    device: &wgpu::Device,
+    label: &str,
+    input: TextureHandle,
+    output: TextureHandle,
+    levels: usize,
+    offset_uniform_buffer: &Arc<wgpu::Buffer>,
+) {
+    let levels = levels.max(1);
+    let format = graph.format(input);
 
-    pub fn input_texture_view(&self) -> &wgpu::TextureView {
-        &self.downsampling.input_texture_view()
+    let mut current = input;
+    for level in 1..levels {
+        let downsampled = graph.add_texture(
+            &format!("{} downsample texture {}", label, level),
+            TextureSize::Fraction(1 << level),
+            format,
+        );
+        let pass_label = format!("{} downsampling {}", label, level);
+        graph.add_pass(
+            &pass_label,
+            &[current],
+            &[Target::Texture(downsampled)],
+            downsampling(device, &pass_label, format, offset_uniform_buffer),
+        );
+        current = downsampled;
     }
 
-    pub fn resize(
-        &mut self,
-        device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
-        queue: &wgpu::Queue,
-    ) {
-        self.downsampling.resize(device, config, queue);
-        self.upsampling.resize(device, config, queue);
-    }
+    // one more pass at the smallest size before going back up
+    let bottom = graph.add_texture(
+        &format!("{} upsample texture {}", label, levels - 1),
+        TextureSize::Fraction(1 << (levels - 1)),
+        format,
+    );
+    let pass_label = format!("{} final downsampling", label);
+    graph.add_pass(
+        &pass_label,
+        &[current],
+        &[Target::Texture(bottom)],
+        downsampling(device, &pass_label, format, offset_uniform_buffer),
+    );
+    current = bottom;
 
-    pub fn render(
-        &self,
-        encoder: &mut wgpu::CommandEncoder,
-        output_view: Option<&wgpu::TextureView>,
-    ) {
-        self.downsampling
-            .render(encoder, Some(self.upsampling.input_texture_view()));
-        self.upsampling.render(encoder, output_view);
+    for level in (0..levels - 1).rev() {
+        let upsampled = graph.add_texture(
+            &format!("{} upsample texture {}", label, level),
+            TextureSize::Fraction(1 << level),
+            format,
+        );
+        let pass_label = format!("{} upsampling {}", label, level);
+        graph.add_pass(
+            &pass_label,
+            &[current],
+            &[Target::Texture(upsampled)],
+            upsampling(device, &pass_label, format, offset_uniform_buffer),
+        );
+        current = upsampled;
     }
+
+    let pass_label = format!("{} final upsampling", label);
+    let output_format = graph.format(output);
+    graph.add_pass(
+        &pass_label,
+        &[current],
+        &[Target::Texture(output)],
+        upsampling(device, &pass_label, output_format, offset_uniform_buffer),
+    );
 }
@@ -113,7 +113,8 @@ impl Camera {
 }
 
 pub struct CameraController {
-    speed: f32,
+    // q and e divide and multiply it by 1.5
+    pub speed: f32,
 
     is_forward_pressed: bool,
     is_backward_pressed: bool,
@@ -345,7 +346,8 @@ impl CameraController {
 
         let pan = dt * self.pan_speed * self.cursor_movement();
 
-        if self.mouse_is_pressed && do_pan {
+        let is_mouse_panning = self.mouse_is_pressed && do_pan && pan != Vec2::ZERO;
+        if is_mouse_panning {
             let rotation = Quat::from_axis_angle(Vec3::Y, pan.x);
             camera.dir = rotation.mul_vec3(camera.dir);
             camera.up = rotation.mul_vec3(camera.up);
@@ -358,10 +360,12 @@ impl CameraController {
             x_movement_norm,
             y_movement_norm,
             z_movement_norm,
+            exp_towards_away_origin_norm,
             x_pan_norm,
             y_pan_norm,
         ]
         .iter()
         .any(|norm| *norm != 0.0)
+            || is_mouse_panning
     }
 }
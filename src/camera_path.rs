@@ -0,0 +1,310 @@
+// scripted camera motion, for rendering fly-throughs
+// positions and the field of view follow a catmull-rom spline through the keyframes
+// and the orientation is slerped between them
+//
+// paths are text files with one keyframe per line:
+// time  pos x,y,z  dir x,y,z  up x,y,z  fov in degrees
+// 0     0,2,-20    0,0,1      0,1,0     90
+// blank lines and anything after a # are ignored and the up vector doesn't have to be
+// perpendicular to dir
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use glam::{Mat4, Quat, Vec3};
+
+use crate::camera::Camera;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe {
+    // seconds
+    pub time: f32,
+    pub pos: Vec3,
+    pub dir: Vec3,
+    pub up: Vec3,
+    // radians, like Camera::fovy
+    pub fovy: f32,
+}
+
+impl Keyframe {
+    pub fn from_camera(time: f32, camera: &Camera) -> Self {
+        Self {
+            time,
+            pos: camera.pos,
+            dir: camera.dir,
+            up: camera.up,
+            fovy: camera.fovy,
+        }
+    }
+
+    // the rotation taking -z to dir and y to the (orthogonalised) up
+    fn orientation(&self) -> Quat {
+        Quat::from_mat4(&Mat4::look_at_rh(Vec3::ZERO, self.dir, self.up).inverse()).normalize()
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.pos = self.pos;
+        camera.dir = self.dir;
+        camera.up = self.up;
+        camera.fovy = self.fovy;
+    }
+}
+
+pub struct CameraPath {
+    // sorted by time
+    pub keyframes: Vec<Keyframe>,
+}
+
+pub fn parse_vec3(s: &str) -> anyhow::Result<Vec3> {
+    let components = s
+        .split(',')
+        .map(|component| component.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("\"{}\" isn't a vector like 1,2,3", s))?;
+    match components[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => bail!("\"{}\" isn't a vector like 1,2,3", s),
+    }
+}
+
+// hermite basis, tangents are per second so they get scaled by the segment length
+fn hermite<T>(p0: T, m0: T, p1: T, m1: T, t: f32, dt: f32) -> T
+where
+    T: std::ops::Mul<f32, Output = T> + std::ops::Add<Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    p0 * (2.0 * t3 - 3.0 * t2 + 1.0)
+        + m0 * ((t3 - 2.0 * t2 + t) * dt)
+        + p1 * (-2.0 * t3 + 3.0 * t2)
+        + m1 * ((t3 - t2) * dt)
+}
+
+impl CameraPath {
+    pub fn new(mut keyframes: Vec<Keyframe>) -> anyhow::Result<Self> {
+        if keyframes.is_empty() {
+            bail!("a camera path needs at least one keyframe");
+        }
+        for keyframe in &keyframes {
+            if keyframe.dir.length_squared() == 0.0
+                || keyframe.dir.cross(keyframe.up).length_squared() == 0.0
+            {
+                bail!(
+                    "the keyframe at {}s has a zero direction or an up parallel to it",
+                    keyframe.time
+                );
+            }
+            if !(keyframe.fovy > 0.0 && keyframe.fovy < std::f32::consts::PI) {
+                bail!(
+                    "the keyframe at {}s has a field of view outside of 0 to 180 degrees",
+                    keyframe.time
+                );
+            }
+        }
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        if keyframes
+            .windows(2)
+            .any(|pair| pair[0].time == pair[1].time)
+        {
+            bail!("two keyframes can't have the same time");
+        }
+        Ok(Self { keyframes })
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut keyframes = vec![];
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let keyframe = (|| {
+                let fields = line.split_whitespace().collect::<Vec<_>>();
+                let [time, pos, dir, up, fov] = fields[..] else {
+                    bail!(
+                        "expected time, pos, dir, up and fov but got {} fields",
+                        fields.len()
+                    );
+                };
+                Ok(Keyframe {
+                    time: time
+                        .parse()
+                        .map_err(|_| anyhow!("\"{}\" isn't a time", time))?,
+                    pos: parse_vec3(pos)?,
+                    dir: parse_vec3(dir)?,
+                    up: parse_vec3(up)?,
+                    fovy: fov
+                        .parse::<f32>()
+                        .map_err(|_| anyhow!("\"{}\" isn't a field of view", fov))?
+                        .to_radians(),
+                })
+            })()
+            .with_context(|| format!("on line {}", line_number + 1))?;
+            keyframes.push(keyframe);
+        }
+        Self::new(keyframes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn start_time(&self) -> f32 {
+        self.keyframes[0].time
+    }
+
+    pub fn end_time(&self) -> f32 {
+        self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.end_time() - self.start_time()
+    }
+
+    // the rate of change at keyframe i, from its neighbours
+    // the ends only have one neighbour so they use that
+    fn tangent<T>(&self, i: usize, value: impl Fn(&Keyframe) -> T) -> T
+    where
+        T: std::ops::Sub<Output = T> + std::ops::Mul<f32, Output = T>,
+    {
+        let before = &self.keyframes[i.saturating_sub(1)];
+        let after = &self.keyframes[(i + 1).min(self.keyframes.len() - 1)];
+        (value(after) - value(before)) * (1.0 / (after.time - before.time))
+    }
+
+    // clamps to the first and last keyframes outside of the path
+    pub fn sample(&self, time: f32) -> Keyframe {
+        let keyframes = &self.keyframes;
+        if keyframes.len() == 1 || time <= self.start_time() {
+            return Keyframe {
+                time,
+                ..keyframes[0]
+            };
+        }
+        if time >= self.end_time() {
+            return Keyframe {
+                time,
+                ..keyframes[keyframes.len() - 1]
+            };
+        }
+
+        // the segment is keyframes[i] to keyframes[i + 1]
+        let i = keyframes.partition_point(|keyframe| keyframe.time <= time) - 1;
+        let (k0, k1) = (&keyframes[i], &keyframes[i + 1]);
+        let dt = k1.time - k0.time;
+        let t = (time - k0.time) / dt;
+
+        let pos = hermite(
+            k0.pos,
+            self.tangent(i, |keyframe| keyframe.pos),
+            k1.pos,
+            self.tangent(i + 1, |keyframe| keyframe.pos),
+            t,
+            dt,
+        );
+        let fovy = hermite(
+            k0.fovy,
+            self.tangent(i, |keyframe| keyframe.fovy),
+            k1.fovy,
+            self.tangent(i + 1, |keyframe| keyframe.fovy),
+            t,
+            dt,
+        )
+        // the spline can overshoot, which could take it to 0 or below between two small ones
+        .clamp(k0.fovy.min(k1.fovy), k0.fovy.max(k1.fovy));
+        let orientation = k0.orientation().slerp(k1.orientation(), t);
+
+        Keyframe {
+            time,
+            pos,
+            dir: orientation * Vec3::NEG_Z,
+            up: orientation * Vec3::Y,
+            fovy,
+        }
+    }
+
+    pub fn apply(&self, time: f32, camera: &mut Camera) {
+        self.sample(time).apply(camera);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "\
+# a comment on its own
+0   0,0,-20  0,0,1   0,1,0  90
+
+1   5,0,-15  -1,0,1  0,1,0  60   # and one after a keyframe
+3   10,2,0   -1,0,0  0,1,1  45
+";
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let path = CameraPath::parse(PATH).unwrap();
+        assert_eq!(path.keyframes.len(), 3);
+        assert_eq!(path.start_time(), 0.0);
+        assert_eq!(path.end_time(), 3.0);
+    }
+
+    #[test]
+    fn keyframes_are_hit_exactly() {
+        let path = CameraPath::parse(PATH).unwrap();
+        for keyframe in &path.keyframes {
+            let sample = path.sample(keyframe.time);
+            assert!(sample.pos.abs_diff_eq(keyframe.pos, 1e-5), "{:?}", sample);
+            assert!(
+                sample.dir.abs_diff_eq(keyframe.dir.normalize(), 1e-5),
+                "{:?}",
+                sample
+            );
+            assert!((sample.fovy - keyframe.fovy).abs() < 1e-6, "{:?}", sample);
+        }
+    }
+
+    #[test]
+    fn samples_are_clamped_to_the_ends() {
+        let path = CameraPath::parse(PATH).unwrap();
+        let before = path.sample(-5.0);
+        assert_eq!(before.time, -5.0);
+        assert_eq!(before.pos, path.keyframes[0].pos);
+        assert_eq!(before.fovy, path.keyframes[0].fovy);
+        let after = path.sample(10.0);
+        assert_eq!(after.pos, path.keyframes[2].pos);
+        assert_eq!(after.fovy, path.keyframes[2].fovy);
+    }
+
+    #[test]
+    fn bad_paths_are_rejected() {
+        let duplicate = "0 0,0,-20 0,0,1 0,1,0 90\n0 0,0,-10 0,0,1 0,1,0 90";
+        assert!(CameraPath::parse(duplicate).is_err());
+        assert!(CameraPath::parse("").is_err());
+        assert!(CameraPath::parse("0 0,0,-20 0,0,1 0,1,0").is_err());
+        assert!(CameraPath::parse("0 0,0,-20 0,0,1 0,0,1 90").is_err());
+        assert!(CameraPath::parse("0 0,0,-20 0,0,1 0,1,0 0").is_err());
+        assert!(CameraPath::parse("zero 0,0,-20 0,0,1 0,1,0 90").is_err());
+    }
+
+    #[test]
+    fn fov_stays_between_its_keyframes() {
+        // the tangent at the middle keyframe comes from the drop before it
+        // so without clamping the spline dips well below 0 after it
+        let path = CameraPath::parse(
+            "0 0,0,-20 0,0,1 0,1,0 90\n1 0,0,-20 0,0,1 0,1,0 1\n10 0,0,-20 0,0,1 0,1,0 1",
+        )
+        .unwrap();
+        for step in 0..=1000 {
+            let sample = path.sample(step as f32 * 0.01);
+            assert!(
+                sample.fovy >= 1f32.to_radians() - 1e-6 && sample.fovy <= 90f32.to_radians() + 1e-6,
+                "{}s: {} degrees",
+                sample.time,
+                sample.fovy.to_degrees()
+            );
+        }
+    }
+}
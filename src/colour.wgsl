@@ -0,0 +1,53 @@
+// the colour of blackbody radiation, for the accretion disk
+
+// cie 1931 colour matching functions, multi-lobe gaussian fit from wyman, sloan & shirley 2013
+fn cie_lobe(wavelength: f32, mu: f32, sigma_1: f32, sigma_2: f32) -> f32 {
+    var t: f32;
+    if wavelength < mu {
+        t = (wavelength - mu) / sigma_1;
+    } else {
+        t = (wavelength - mu) / sigma_2;
+    }
+    return exp(-0.5 * t * t);
+}
+
+fn cie_xyz(wavelength: f32) -> vec3<f32> {
+    let x = 1.056 * cie_lobe(wavelength, 599.8, 37.9, 31.0)
+        + 0.362 * cie_lobe(wavelength, 442.0, 16.0, 26.7)
+        - 0.065 * cie_lobe(wavelength, 501.1, 20.4, 26.2);
+    let y = 0.821 * cie_lobe(wavelength, 568.8, 46.9, 40.5)
+        + 0.286 * cie_lobe(wavelength, 530.9, 16.3, 31.1);
+    let z = 1.217 * cie_lobe(wavelength, 437.0, 11.8, 36.0)
+        + 0.681 * cie_lobe(wavelength, 459.0, 26.0, 13.8);
+    return vec3<f32>(x, y, z);
+}
+
+// planck's law without the constant in front, wavelength in nm
+fn planck(wavelength: f32, temperature: f32) -> f32 {
+    // hc / k in nm K
+    let c2 = 1.4388e7;
+    let w = wavelength * 1e-3;
+    return 1.0 / (w * w * w * w * w * (exp(c2 / (wavelength * temperature)) - 1.0));
+}
+
+const BLACKBODY_SAMPLES = 24;
+
+// linear srgb colour of a blackbody, scaled to a luminance of 1
+fn blackbody_col(temperature: f32) -> vec3<f32> {
+    var xyz = vec3<f32>(0.0);
+    for (var i = 0; i < BLACKBODY_SAMPLES; i++) {
+        let wavelength = mix(380.0, 780.0, (f32(i) + 0.5) / f32(BLACKBODY_SAMPLES));
+        xyz += planck(wavelength, temperature) * cie_xyz(wavelength);
+    }
+    if xyz.y <= 0.0 {
+        return vec3<f32>(0.0);
+    }
+    xyz /= xyz.y;
+    let rgb = vec3<f32>(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    );
+    // the reddest and bluest blackbodies are outside srgb
+    return max(rgb, vec3<f32>(0.0));
+}
@@ -1,20 +1,17 @@
-struct VertexOutput {
-    // Mark output position as invariant so it's safe to use it with depth test Equal.
-    // Without @invariant, different usages in different render pipelines might optimize differently,
-    // causing slightly different results.
-    @invariant @builtin(position)
-    position: vec4f,
-    @location(0)
-    texcoord: vec2f,
-};
+#include "src/fullscreen.wgsl"
 
 @group(0) @binding(0)
 var input_texture: texture_2d<f32>;
 @group(0) @binding(1)
 var input_texture_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> threshold: f32;
 
 @fragment
 fn main(in: VertexOutput) -> @location(0) vec4<f32> {
     let col = textureSampleLevel(input_texture, input_texture_sampler, in.texcoord, 0.0);
+    if dot(col.rgb, col.rgb) < threshold * threshold {
+        return vec4f(0.0, 0.0, 0.0, col.a);
+    }
     return col;
 }
\ No newline at end of file
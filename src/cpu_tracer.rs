@@ -0,0 +1,846 @@
+// a cpu copy of the photon integrator in integrator.wgsl and the ray marching in black_hole_maybe.wgsl
+// everything in here should do exactly what the shader does (in f32)
+// so that the physics can be checked without a gpu
+// if you change the shader, change this too
+// the volumetric disk isn't in here, rays always stop at the thin disk
+
+use glam::{vec2, vec3, Quat, Vec2, Vec3};
+
+use crate::camera::Camera;
+
+pub const MIN_DIST: f32 = 0.001;
+pub const MAX_ITERATIONS: usize = 1000;
+pub const DELTA_TIME_MULT: f32 = 0.5;
+
+pub const DISK_PEAK_TEMPERATURE: f32 = 6500.0;
+pub const DISK_REFERENCE_FLUX: f32 = 1.3678e-5;
+
+// mirrors the INTEGRATOR_ constants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integrator {
+    Rk4,
+    Rk45,
+    Binet,
+}
+
+impl From<u32> for Integrator {
+    // anything the shader doesn't know about falls back to rk4, like it does in the shader
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Integrator::Rk45,
+            2 => Integrator::Binet,
+            _ => Integrator::Rk4,
+        }
+    }
+}
+
+// mirrors the Uniforms struct in the shader
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TracerUniforms {
+    pub rs: f32,
+    pub log_tolerance: f32,
+    pub blackout_eh: bool,
+    pub max_dist: f32,
+    pub distortion_power: f32,
+    pub kerr: bool,
+    pub spin: f32,
+    pub integrator: Integrator,
+    pub disk_inner_radius: f32,
+    pub disk_outer_radius: f32,
+    pub accretion_rate: f32,
+    pub disk_inclination: f32,
+    pub redshift: bool,
+    pub beaming_exponent: f32,
+}
+
+impl Default for TracerUniforms {
+    // same as the starting values in Scene::new
+    fn default() -> Self {
+        Self {
+            rs: 1.0,
+            log_tolerance: -5.0,
+            blackout_eh: true,
+            max_dist: 250.0,
+            distortion_power: 1.0,
+            kerr: false,
+            spin: 0.9,
+            integrator: Integrator::Rk4,
+            disk_inner_radius: 1.0,
+            disk_outer_radius: 10.0,
+            accretion_rate: 1.0,
+            disk_inclination: 0.0,
+            redshift: true,
+            beaming_exponent: 4.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Photon {
+    pub ro: Vec3,
+    pub rd: Vec3,
+}
+
+impl Photon {
+    // the same ray fs_main starts with for the given clip space position
+    pub fn from_camera(camera: &Camera, clip_position: Vec2) -> Self {
+        Self {
+            ro: camera.pos,
+            rd: camera.clip_position_to_world_direction(clip_position).normalize(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceResult {
+    // the photon got far enough away (or ran out of iterations) and carries on in this direction
+    // this is what gets looked up in the background texture
+    Escaped { direction: Vec3, iterations: usize },
+    // the photon hit the accretion disk, in the frame of the disk
+    // photon.rd is the momentum for kerr and the velocity for schwarzschild, like in the shader
+    Disk { photon: Photon, iterations: usize },
+    // the photon hit one of the markers
+    Hit { position: Vec3, iterations: usize },
+    // the photon fell into the black hole
+    Blackout { position: Vec3, iterations: usize },
+}
+
+impl TraceResult {
+    pub fn iterations(&self) -> usize {
+        match *self {
+            TraceResult::Escaped { iterations, .. } => iterations,
+            TraceResult::Disk { iterations, .. } => iterations,
+            TraceResult::Hit { iterations, .. } => iterations,
+            TraceResult::Blackout { iterations, .. } => iterations,
+        }
+    }
+}
+
+pub fn sdf_sphere(p: Vec3, centre: Vec3, r: f32) -> f32 {
+    (centre - p).length() - r
+}
+
+pub fn sdf_plane(p: Vec3, y: f32) -> f32 {
+    (p.y - y).abs() - 0.02
+}
+
+pub fn sdf_cylinder(p: Vec3, pos: Vec2, radius: f32) -> f32 {
+    (vec2(p.x, p.z) - pos).length() - radius
+}
+
+pub fn sdf_accretion_disk(p: Vec3, centre: Vec3, big_r: f32, little_r: f32) -> f32 {
+    sdf_cylinder(p, vec2(centre.x, centre.z), big_r)
+        .max(-sdf_cylinder(p, vec2(centre.x, centre.z), little_r))
+        .max(sdf_plane(p, centre.y))
+}
+
+pub fn sdf_markers(p: Vec3) -> f32 {
+    let sd_sphere_1 = sdf_sphere(p, vec3(0.0, 10.0, -10.0), 0.5);
+    let sd_sphere_2 = sdf_sphere(p, vec3(0.0, -10.0, -10.0), 0.5);
+    let sd_sphere_3 = sdf_sphere(p, vec3(10.0, 0.0, -10.0), 0.5);
+    let sd_sphere_4 = sdf_sphere(p, vec3(-10.0, 0.0, -10.0), 0.5);
+    sd_sphere_1.min(sd_sphere_2.min(sd_sphere_3.min(sd_sphere_4)))
+}
+
+pub fn disk_spin(u: &TracerUniforms) -> f32 {
+    if u.kerr {
+        return kerr_spin(u);
+    }
+    0.0
+}
+
+pub fn disk_inner_r(u: &TracerUniforms) -> f32 {
+    u.disk_inner_radius.max(1.0) * isco_radius(black_hole_mass(u), disk_spin(u))
+}
+
+pub fn disk_inner_radius(u: &TracerUniforms) -> f32 {
+    let r_in = disk_inner_r(u);
+    if u.kerr {
+        let a = kerr_a(u);
+        return (r_in * r_in + a * a).sqrt();
+    }
+    r_in
+}
+
+pub fn sdf_disk(p: Vec3, u: &TracerUniforms) -> f32 {
+    sdf_accretion_disk(p, Vec3::ZERO, u.disk_outer_radius * u.rs, disk_inner_radius(u))
+}
+
+pub fn to_disk_frame(v: Vec3, u: &TracerUniforms) -> Vec3 {
+    Quat::from_rotation_x(-u.disk_inclination.to_radians()) * v
+}
+
+pub fn from_disk_frame(v: Vec3, u: &TracerUniforms) -> Vec3 {
+    Quat::from_rotation_x(u.disk_inclination.to_radians()) * v
+}
+
+pub fn rd_derivative(ro: Vec3, h2: f32, u: &TracerUniforms) -> Vec3 {
+    u.distortion_power * u.rs * -1.5 * h2 * ro / ro.dot(ro).powf(2.5)
+}
+
+pub fn get_delta_photon_rk4(photon: Photon, delta_time: f32, h2: f32, u: &TracerUniforms) -> Photon {
+    let ro_k1 = delta_time * photon.rd;
+    let rd_k1 = delta_time * rd_derivative(photon.ro, h2, u);
+
+    let ro_k2 = delta_time * (photon.rd + 0.5 * rd_k1);
+    let rd_k2 = delta_time * rd_derivative(photon.ro + 0.5 * ro_k1, h2, u);
+
+    let ro_k3 = delta_time * (photon.rd + 0.5 * rd_k2);
+    let rd_k3 = delta_time * rd_derivative(photon.ro + 0.5 * ro_k2, h2, u);
+
+    let ro_k4 = delta_time * (photon.rd + rd_k3);
+    let rd_k4 = delta_time * rd_derivative(photon.ro + ro_k3, h2, u);
+
+    let delta_ro = (ro_k1 + 2.0 * ro_k2 + 2.0 * ro_k3 + ro_k4) / 6.0;
+    let delta_rd = (rd_k1 + 2.0 * rd_k2 + 2.0 * rd_k3 + rd_k4) / 6.0;
+
+    Photon {
+        ro: delta_ro,
+        rd: delta_rd,
+    }
+}
+
+// kerr, see the shader for what all of this means
+
+pub fn black_hole_mass(u: &TracerUniforms) -> f32 {
+    0.5 * u.distortion_power * u.rs
+}
+
+pub fn kerr_spin(u: &TracerUniforms) -> f32 {
+    u.spin.clamp(-0.999, 0.999)
+}
+
+pub fn kerr_a(u: &TracerUniforms) -> f32 {
+    kerr_spin(u) * black_hole_mass(u)
+}
+
+pub fn kerr_trace_a(u: &TracerUniforms) -> f32 {
+    -kerr_a(u)
+}
+
+pub fn kerr_horizon_radius(u: &TracerUniforms) -> f32 {
+    let m = black_hole_mass(u);
+    let a = kerr_a(u);
+    m + (m * m - a * a).sqrt()
+}
+
+pub fn isco_radius(m: f32, spin: f32) -> f32 {
+    let a = spin.abs();
+    let z1 = 1.0
+        + (1.0 - a * a).powf(1.0 / 3.0) * ((1.0 + a).powf(1.0 / 3.0) + (1.0 - a).powf(1.0 / 3.0));
+    let z2 = (3.0 * a * a + z1 * z1).sqrt();
+    m * (3.0 + z2 - ((3.0 - z1) * (3.0 + z1 + 2.0 * z2)).sqrt())
+}
+
+pub fn kerr_r(p: Vec3, a: f32) -> f32 {
+    let b = 0.5 * (p.dot(p) - a * a);
+    (b + (b * b + a * a * p.y * p.y).sqrt()).sqrt()
+}
+
+struct KerrSchild {
+    f: f32,
+    l: Vec3,
+}
+
+fn kerr_schild(p: Vec3, m: f32, a: f32) -> KerrSchild {
+    let r = kerr_r(p, a);
+    let r2 = r * r;
+    let a2 = a * a;
+    let f = 2.0 * m * r2 * r / (r2 * r2 + a2 * p.y * p.y);
+    let l = vec3(
+        (r * p.x - a * p.z) / (r2 + a2),
+        p.y / r,
+        (r * p.z + a * p.x) / (r2 + a2),
+    );
+    KerrSchild { f, l }
+}
+
+fn kerr_potential(p: Vec3, momentum: Vec3, m: f32, a: f32) -> f32 {
+    let ks = kerr_schild(p, m, a);
+    let l_dot_p = 1.0 + ks.l.dot(momentum);
+    ks.f * l_dot_p * l_dot_p
+}
+
+pub fn kerr_photon_derivative(photon: Photon, m: f32, a: f32) -> Photon {
+    let ks = kerr_schild(photon.ro, m, a);
+    let l_dot_p = 1.0 + ks.l.dot(photon.rd);
+    let d_ro = photon.rd - ks.f * l_dot_p * ks.l;
+    let e = 1e-3 * photon.ro.length().max(1.0);
+    let ex = vec3(e, 0.0, 0.0);
+    let ey = vec3(0.0, e, 0.0);
+    let ez = vec3(0.0, 0.0, e);
+    let grad = vec3(
+        kerr_potential(photon.ro + ex, photon.rd, m, a)
+            - kerr_potential(photon.ro - ex, photon.rd, m, a),
+        kerr_potential(photon.ro + ey, photon.rd, m, a)
+            - kerr_potential(photon.ro - ey, photon.rd, m, a),
+        kerr_potential(photon.ro + ez, photon.rd, m, a)
+            - kerr_potential(photon.ro - ez, photon.rd, m, a),
+    ) / (2.0 * e);
+    Photon {
+        ro: d_ro,
+        rd: 0.5 * grad,
+    }
+}
+
+pub fn kerr_initial_momentum(ro: Vec3, rd: Vec3, m: f32, a: f32) -> Vec3 {
+    let ks = kerr_schild(ro, m, a);
+    let l_dot_d = ks.l.dot(rd);
+    let qa = 1.0 - ks.f * l_dot_d * l_dot_d;
+    let qb = -2.0 * ks.f * l_dot_d;
+    let qc = -(1.0 + ks.f);
+    if qa.abs() < 1e-6 {
+        return rd;
+    }
+    let k = (-qb + (qb * qb - 4.0 * qa * qc).max(0.0).sqrt()) / (2.0 * qa);
+    k * rd
+}
+
+pub fn get_delta_photon_kerr_rk4(photon: Photon, delta_distance: f32, m: f32, a: f32) -> Photon {
+    let offset = |k: Photon, scale: f32| Photon {
+        ro: photon.ro + scale * k.ro,
+        rd: photon.rd + scale * k.rd,
+    };
+    let times = |d: Photon, delta_time: f32| Photon {
+        ro: delta_time * d.ro,
+        rd: delta_time * d.rd,
+    };
+
+    let d1 = kerr_photon_derivative(photon, m, a);
+    let delta_time = delta_distance / d1.ro.length().max(1e-6);
+
+    let k1 = times(d1, delta_time);
+    let k2 = times(kerr_photon_derivative(offset(k1, 0.5), m, a), delta_time);
+    let k3 = times(kerr_photon_derivative(offset(k2, 0.5), m, a), delta_time);
+    let k4 = times(kerr_photon_derivative(offset(k3, 1.0), m, a), delta_time);
+
+    Photon {
+        ro: (k1.ro + 2.0 * k2.ro + 2.0 * k3.ro + k4.ro) / 6.0,
+        rd: (k1.rd + 2.0 * k2.rd + 2.0 * k3.rd + k4.rd) / 6.0,
+    }
+}
+
+pub fn photon_derivative(photon: Photon, h2: f32, u: &TracerUniforms) -> Photon {
+    if u.kerr {
+        return kerr_photon_derivative(photon, black_hole_mass(u), kerr_trace_a(u));
+    }
+    Photon {
+        ro: photon.rd,
+        rd: rd_derivative(photon.ro, h2, u),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveStep {
+    pub delta: Photon,
+    pub k_end: Photon,
+    pub error_ratio: f32,
+}
+
+// one dormand-prince 5(4) step, same as get_delta_photon_rk45 in the shader
+pub fn get_delta_photon_rk45(
+    photon: Photon,
+    k1: Photon,
+    delta_time: f32,
+    tolerance: f32,
+    h2: f32,
+    u: &TracerUniforms,
+) -> AdaptiveStep {
+    let h = delta_time;
+    // y = photon + h * sum(a_i * k_i)
+    let stage = |ks: &[(Photon, f32)]| {
+        let mut y = photon;
+        for (k, a) in ks {
+            y.ro += h * a * k.ro;
+            y.rd += h * a * k.rd;
+        }
+        photon_derivative(y, h2, u)
+    };
+
+    let k2 = stage(&[(k1, 1.0 / 5.0)]);
+    let k3 = stage(&[(k1, 3.0 / 40.0), (k2, 9.0 / 40.0)]);
+    let k4 = stage(&[(k1, 44.0 / 45.0), (k2, -56.0 / 15.0), (k3, 32.0 / 9.0)]);
+    let k5 = stage(&[
+        (k1, 19372.0 / 6561.0),
+        (k2, -25360.0 / 2187.0),
+        (k3, 64448.0 / 6561.0),
+        (k4, -212.0 / 729.0),
+    ]);
+    let k6 = stage(&[
+        (k1, 9017.0 / 3168.0),
+        (k2, -355.0 / 33.0),
+        (k3, 46732.0 / 5247.0),
+        (k4, 49.0 / 176.0),
+        (k5, -5103.0 / 18656.0),
+    ]);
+
+    let fifth_order = [
+        (k1, 35.0 / 384.0),
+        (k3, 500.0 / 1113.0),
+        (k4, 125.0 / 192.0),
+        (k5, -2187.0 / 6784.0),
+        (k6, 11.0 / 84.0),
+    ];
+    let mut delta = Photon {
+        ro: Vec3::ZERO,
+        rd: Vec3::ZERO,
+    };
+    for (k, b) in fifth_order {
+        delta.ro += h * b * k.ro;
+        delta.rd += h * b * k.rd;
+    }
+    let k7 = photon_derivative(
+        Photon {
+            ro: photon.ro + delta.ro,
+            rd: photon.rd + delta.rd,
+        },
+        h2,
+        u,
+    );
+
+    let error_weights = [
+        (k1, 71.0 / 57600.0),
+        (k3, -71.0 / 16695.0),
+        (k4, 71.0 / 1920.0),
+        (k5, -17253.0 / 339200.0),
+        (k6, 22.0 / 525.0),
+        (k7, -1.0 / 40.0),
+    ];
+    let mut error_ro = Vec3::ZERO;
+    let mut error_rd = Vec3::ZERO;
+    for (k, e) in error_weights {
+        error_ro += h * e * k.ro;
+        error_rd += h * e * k.rd;
+    }
+
+    let error_ratio = (error_ro.length() / (tolerance * photon.ro.length().max(1.0)))
+        .max(error_rd.length() / (tolerance * photon.rd.length().max(1.0)));
+
+    AdaptiveStep {
+        delta,
+        k_end: k7,
+        error_ratio,
+    }
+}
+
+// the binet equation in the plane of the photon, see Orbit in the shader
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orbit {
+    pub e1: Vec3,
+    pub e2: Vec3,
+    pub h: f32,
+    pub u: f32,
+    pub w: f32,
+    pub phi: f32,
+}
+
+impl Orbit {
+    pub fn from_photon(photon: Photon, h2: f32) -> Self {
+        let r = photon.ro.length();
+        let e1 = photon.ro / r;
+        let h = h2.sqrt();
+        Self {
+            e1,
+            e2: (photon.rd - photon.rd.dot(e1) * e1).normalize(),
+            h,
+            u: 1.0 / r,
+            w: -photon.rd.dot(e1) / h,
+            phi: 0.0,
+        }
+    }
+
+    pub fn step(self, delta_phi: f32, m: f32) -> Self {
+        let acceleration = |u: f32| 3.0 * m * u * u - u;
+        let u_k1 = self.w;
+        let w_k1 = acceleration(self.u);
+        let u_k2 = self.w + 0.5 * delta_phi * w_k1;
+        let w_k2 = acceleration(self.u + 0.5 * delta_phi * u_k1);
+        let u_k3 = self.w + 0.5 * delta_phi * w_k2;
+        let w_k3 = acceleration(self.u + 0.5 * delta_phi * u_k2);
+        let u_k4 = self.w + delta_phi * w_k3;
+        let w_k4 = acceleration(self.u + delta_phi * u_k3);
+        Self {
+            u: self.u + delta_phi * (u_k1 + 2.0 * u_k2 + 2.0 * u_k3 + u_k4) / 6.0,
+            w: self.w + delta_phi * (w_k1 + 2.0 * w_k2 + 2.0 * w_k3 + w_k4) / 6.0,
+            phi: self.phi + delta_phi,
+            ..self
+        }
+    }
+
+    pub fn delta_phi(&self, delta_distance: f32) -> f32 {
+        delta_distance * self.u * self.u / vec2(self.u, self.w).length()
+    }
+
+    pub fn photon(&self) -> Photon {
+        let radial = self.phi.cos() * self.e1 + self.phi.sin() * self.e2;
+        let tangential = self.phi.cos() * self.e2 - self.phi.sin() * self.e1;
+        let u = self.u.max(0.0);
+        Photon {
+            ro: radial / u.max(1e-6),
+            rd: self.h * (u * tangential - self.w * radial),
+        }
+    }
+}
+
+// accretion disk emission, the blackbody colour is left to the shader
+
+pub fn novikov_thorne_flux(r: f32, r_in: f32, spin: f32) -> f32 {
+    if r <= r_in {
+        return 0.0;
+    }
+    let a = spin;
+    let x = r.sqrt();
+    let x0 = r_in.sqrt();
+    let acos_a = a.acos();
+    let x1 = 2.0 * ((acos_a - std::f32::consts::PI) / 3.0).cos();
+    let x2 = 2.0 * ((acos_a + std::f32::consts::PI) / 3.0).cos();
+    let x3 = -2.0 * (acos_a / 3.0).cos();
+
+    let bracket = x - x0 - 1.5 * a * (x / x0).ln()
+        - novikov_thorne_log_term(x, x0, a, x1, x2, x3)
+        - novikov_thorne_log_term(x, x0, a, x2, x3, x1)
+        - novikov_thorne_log_term(x, x0, a, x3, x1, x2);
+    let xx = x * x;
+    3.0 / (8.0 * std::f32::consts::PI) * bracket / (xx * xx * (xx * x - 3.0 * x + 2.0 * a))
+}
+
+fn novikov_thorne_log_term(x: f32, x0: f32, a: f32, xi: f32, xj: f32, xk: f32) -> f32 {
+    if xi.abs() < 1e-4 {
+        return 0.0;
+    }
+    3.0 * (xi - a) * (xi - a) / (xi * (xi - xj) * (xi - xk)) * ((x - xi) / (x0 - xi)).ln()
+}
+
+pub fn disk_temperature(flux: f32) -> f32 {
+    DISK_PEAK_TEMPERATURE * (flux.max(0.0) / DISK_REFERENCE_FLUX).powf(0.25)
+}
+
+// boyer-lindquist radius of a point on the disk, in the disk frame
+pub fn disk_r(p: Vec3, u: &TracerUniforms) -> f32 {
+    if u.kerr {
+        kerr_r(p, kerr_a(u))
+    } else {
+        vec2(p.x, p.z).length()
+    }
+}
+
+// the flux disk_col uses for a point on the disk, in the disk frame
+pub fn disk_flux(p: Vec3, u: &TracerUniforms) -> f32 {
+    let m = black_hole_mass(u);
+    u.accretion_rate * novikov_thorne_flux(disk_r(p, u) / m, disk_inner_r(u) / m, disk_spin(u).abs())
+}
+
+pub fn disk_redshift(photon: Photon, r: f32, m: f32, spin: f32) -> f32 {
+    let orbit_sign = if spin < 0.0 { -1.0 } else { 1.0 };
+    let a = spin.abs() * m;
+    let p_phi = -photon.ro.cross(photon.rd).y;
+    let sqrt_m = m.sqrt();
+    let r_3_2 = r * r.sqrt();
+    let omega = orbit_sign * sqrt_m / (r_3_2 + a * sqrt_m);
+    let u_t = (r_3_2 + a * sqrt_m)
+        / (r_3_2 * (r_3_2 - 3.0 * m * r.sqrt() + 2.0 * a * sqrt_m)).max(1e-6).sqrt();
+    1.0 / (u_t * (1.0 - omega * p_phi))
+}
+
+// the loop in get_col, without the colour lookup at the end
+pub fn trace(initial_photon: Photon, u: &TracerUniforms) -> TraceResult {
+    march(initial_photon, u, true)
+}
+
+// the same loop with nothing in the way, so it only escapes or falls in
+// this is what the deflection lookup table is made of
+pub fn trace_background(initial_photon: Photon, u: &TracerUniforms) -> TraceResult {
+    march(initial_photon, u, false)
+}
+
+fn march(initial_photon: Photon, u: &TracerUniforms, surfaces: bool) -> TraceResult {
+    let initial_ro = to_disk_frame(initial_photon.ro, u);
+    let mut photon = Photon {
+        ro: initial_ro,
+        rd: to_disk_frame(initial_photon.rd, u),
+    };
+
+    let initial_ro_rd_cross = photon.ro.cross(photon.rd);
+    let h2 = initial_ro_rd_cross.dot(initial_ro_rd_cross);
+
+    let kerr_m = black_hole_mass(u);
+    let kerr_spin_a = kerr_trace_a(u);
+    let kerr_r_horizon = kerr_horizon_radius(u);
+    if u.kerr {
+        photon.rd = kerr_initial_momentum(photon.ro, photon.rd, kerr_m, kerr_spin_a);
+    }
+
+    let adaptive = u.integrator == Integrator::Rk45;
+    let binet = u.integrator == Integrator::Binet && !u.kerr && h2 > 1e-12;
+    let mut orbit = Orbit::from_photon(photon, h2);
+    let tolerance = 10f32.powf(u.log_tolerance);
+    let mut adaptive_delta_time = DELTA_TIME_MULT * photon.ro.length();
+    let mut k1 = photon_derivative(photon, h2, u);
+
+    let mut distance_travelled = 0.0;
+    let mut has_been_outside_eh = false;
+    let mut iterations = 0;
+    while iterations < MAX_ITERATIONS {
+        let dist_to_singularity = photon.ro.length();
+        let blackout = TraceResult::Blackout {
+            position: photon.ro,
+            iterations,
+        };
+        if u.kerr {
+            if kerr_r(photon.ro, kerr_spin_a) < kerr_r_horizon {
+                return blackout;
+            }
+        } else if u.blackout_eh {
+            if dist_to_singularity < 1.0 && photon.rd.dot(photon.ro) < 0.0 {
+                return blackout;
+            }
+            if dist_to_singularity > 1.0 {
+                has_been_outside_eh = true;
+            } else if has_been_outside_eh {
+                return blackout;
+            }
+        }
+
+        let dist = if surfaces {
+            let dist_to_disk = sdf_disk(photon.ro, u);
+            if dist_to_disk < MIN_DIST {
+                return TraceResult::Disk {
+                    photon,
+                    iterations,
+                };
+            }
+            let dist_to_markers = sdf_markers(from_disk_frame(photon.ro, u));
+            if dist_to_markers < MIN_DIST {
+                return TraceResult::Hit {
+                    position: photon.ro,
+                    iterations,
+                };
+            }
+            dist_to_disk.min(dist_to_markers)
+        } else {
+            f32::INFINITY
+        };
+
+        let dd = (DELTA_TIME_MULT * dist_to_singularity).min(dist * 0.9);
+
+        if binet {
+            orbit = orbit.step(orbit.delta_phi(dd), kerr_m);
+            photon = orbit.photon();
+            iterations += 1;
+            distance_travelled += dd;
+            if distance_travelled > u.max_dist || orbit.u <= 0.0 {
+                break;
+            }
+            continue;
+        }
+
+        if adaptive {
+            let speed = k1.ro.length().max(1e-6);
+            let delta_time = adaptive_delta_time.min(dd / speed);
+            let step = get_delta_photon_rk45(photon, k1, delta_time, tolerance, h2, u);
+            let step_scale = 0.9 * step.error_ratio.max(1e-10).powf(-0.2);
+            adaptive_delta_time = delta_time * step_scale.clamp(0.2, 5.0);
+            iterations += 1;
+            if step.error_ratio > 1.0 {
+                continue;
+            }
+            photon.ro += step.delta.ro;
+            photon.rd += step.delta.rd;
+            k1 = step.k_end;
+            distance_travelled += delta_time * speed;
+            if distance_travelled > u.max_dist {
+                break;
+            }
+            continue;
+        }
+
+        let delta_photon = if u.kerr {
+            get_delta_photon_kerr_rk4(photon, dd, kerr_m, kerr_spin_a)
+        } else {
+            get_delta_photon_rk4(photon, dd, h2, u)
+        };
+
+        photon.ro += delta_photon.ro;
+        photon.rd += delta_photon.rd;
+
+        iterations += 1;
+
+        distance_travelled += dd;
+        if distance_travelled > u.max_dist {
+            break;
+        }
+    }
+    TraceResult::Escaped {
+        direction: from_disk_frame(photon.rd.normalize(), u),
+        iterations,
+    }
+}
+
+// where an escaped direction lands on the background texture, same as the end of get_col
+// (0, 0) is the top left
+pub fn direction_to_background_uv(direction: Vec3) -> Vec2 {
+    let normalized_final_rd = direction.normalize();
+    let azimuthal_angle = normalized_final_rd.z.atan2(normalized_final_rd.x);
+    let x = (azimuthal_angle + std::f32::consts::PI) / std::f32::consts::TAU;
+    let y = (normalized_final_rd.y + 1.0) * 0.5;
+    vec2(x, 1.0 - y)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // far enough that the photon starts and ends out of the black hole's way
+    const FAR: f32 = 1.0e4;
+
+    fn uniforms() -> TracerUniforms {
+        TracerUniforms {
+            max_dist: 3.0 * FAR,
+            ..Default::default()
+        }
+    }
+
+    // a photon coming in along +z, passing the black hole at impact_parameter along +x
+    fn incoming(impact_parameter: f32, distance: f32) -> Photon {
+        Photon {
+            ro: vec3(impact_parameter, 0.0, -distance),
+            rd: vec3(0.0, 0.0, 1.0),
+        }
+    }
+
+    // how far an escaping photon has turned, in radians
+    fn deflection(impact_parameter: f32, u: &TracerUniforms) -> f32 {
+        match trace_background(incoming(impact_parameter, FAR), u) {
+            TraceResult::Escaped { direction, .. } => direction.angle_between(vec3(0.0, 0.0, 1.0)),
+            result => panic!("a photon at b = {} didn't escape: {:?}", impact_parameter, result),
+        }
+    }
+
+    fn escaped_direction(impact_parameter: f32, u: &TracerUniforms) -> Vec3 {
+        match trace_background(incoming(impact_parameter, 100.0), u) {
+            TraceResult::Escaped { direction, .. } => direction,
+            result => panic!("a photon at b = {} didn't escape: {:?}", impact_parameter, result),
+        }
+    }
+
+    // the value of `const name = value;` in a shader
+    pub(crate) fn shader_const(source: &str, name: &str) -> f32 {
+        source
+            .lines()
+            .find_map(|line| {
+                let rest = line.trim().strip_prefix("const ")?.strip_prefix(name)?;
+                // not just a const whose name starts with this one
+                let (declaration, value) = rest.split_once('=')?;
+                if !(declaration.is_empty() || declaration.starts_with([' ', ':'])) {
+                    return None;
+                }
+                value.trim().strip_suffix(';')?.trim().parse().ok()
+            })
+            .unwrap_or_else(|| panic!("the shader has no const {}", name))
+    }
+
+    #[test]
+    fn constants_match_the_shader() {
+        let shader = include_str!("black_hole_maybe.wgsl");
+        assert_eq!(shader_const(shader, "MIN_DIST"), MIN_DIST);
+        assert_eq!(shader_const(shader, "MAX_ITERATIONS"), MAX_ITERATIONS as f32);
+        assert_eq!(shader_const(shader, "DELTA_TIME_MULT"), DELTA_TIME_MULT);
+        assert_eq!(shader_const(shader, "DISK_PEAK_TEMPERATURE"), DISK_PEAK_TEMPERATURE);
+        assert_eq!(shader_const(shader, "DISK_REFERENCE_FLUX"), DISK_REFERENCE_FLUX);
+    }
+
+    #[test]
+    fn weak_field_deflection_is_2_rs_over_b() {
+        let u = uniforms();
+        for impact_parameter in [50.0, 100.0, 200.0] {
+            let expected = 2.0 * u.rs / impact_parameter;
+            let actual = deflection(impact_parameter, &u);
+            assert!(
+                (actual - expected).abs() < 0.05 * expected,
+                "b = {}: deflected {} rather than {}",
+                impact_parameter,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn photons_bend_towards_the_black_hole() {
+        let direction = escaped_direction(10.0, &uniforms());
+        assert!(direction.x < 0.0, "{:?}", direction);
+    }
+
+    #[test]
+    fn shadow_edge_is_at_the_critical_impact_parameter() {
+        let u = uniforms();
+        let critical = 1.5 * 3f32.sqrt() * u.rs;
+        for integrator in [Integrator::Rk4, Integrator::Rk45, Integrator::Binet] {
+            let u = TracerUniforms { integrator, ..u };
+            for impact_parameter in [0.5 * critical, 0.97 * critical] {
+                let result = trace_background(incoming(impact_parameter, 100.0), &u);
+                assert!(
+                    matches!(result, TraceResult::Blackout { .. }),
+                    "{:?}, b = {}: {:?}",
+                    integrator,
+                    impact_parameter,
+                    result
+                );
+            }
+            for impact_parameter in [1.03 * critical, 2.0 * critical] {
+                let result = trace_background(incoming(impact_parameter, 100.0), &u);
+                assert!(
+                    matches!(result, TraceResult::Escaped { .. }),
+                    "{:?}, b = {}: {:?}",
+                    integrator,
+                    impact_parameter,
+                    result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn markers_are_hit() {
+        let photon = Photon {
+            ro: vec3(0.0, 10.0, -30.0),
+            rd: vec3(0.0, 0.0, 1.0),
+        };
+        let result = trace(photon, &uniforms());
+        assert!(matches!(result, TraceResult::Hit { .. }), "{:?}", result);
+    }
+
+    #[test]
+    fn rk4_and_rk45_agree() {
+        let rk4 = uniforms();
+        let rk45 = TracerUniforms {
+            integrator: Integrator::Rk45,
+            ..rk4
+        };
+        for impact_parameter in [3.5, 4.0, 6.0, 10.0] {
+            let angle = escaped_direction(impact_parameter, &rk4)
+                .angle_between(escaped_direction(impact_parameter, &rk45));
+            assert!(angle < 0.01, "b = {}: {} radians apart", impact_parameter, angle);
+        }
+    }
+
+    #[test]
+    fn kerr_without_spin_is_schwarzschild() {
+        let schwarzschild = uniforms();
+        let kerr = TracerUniforms {
+            kerr: true,
+            spin: 1.0e-3,
+            ..schwarzschild
+        };
+        for impact_parameter in [3.5, 4.0, 6.0, 10.0] {
+            let angle = escaped_direction(impact_parameter, &schwarzschild)
+                .angle_between(escaped_direction(impact_parameter, &kerr));
+            assert!(angle < 0.01, "b = {}: {} radians apart", impact_parameter, angle);
+        }
+        let critical = 1.5 * 3f32.sqrt() * schwarzschild.rs;
+        let result = trace_background(incoming(0.97 * critical, 100.0), &kerr);
+        assert!(matches!(result, TraceResult::Blackout { .. }), "{:?}", result);
+        let result = trace_background(incoming(1.03 * critical, 100.0), &kerr);
+        assert!(matches!(result, TraceResult::Escaped { .. }), "{:?}", result);
+    }
+}
@@ -0,0 +1,624 @@
+// a lookup table of where the rays around a non spinning black hole end up
+// everything is symmetric about the black hole, so a ray only depends on how far away it
+// starts and the angle it makes with the way to the black hole
+// it stays in the plane with the black hole in it, and the table has how far it turns in that
+// plane, so the shader does one lookup and a rotation instead of marching
+// the disk and the markers aren't in it, they're found with the orbit table further down
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc;
+
+use glam::{vec3, Vec3};
+
+use crate::cpu_tracer::{self, Photon, TraceResult, TracerUniforms};
+
+// has to match the DEFLECTION_LUT_ constants in black_hole_maybe.wgsl
+pub const RADII: u32 = 256;
+pub const ANGLES: u32 = 512;
+pub const MIN_RADIUS: f32 = 1.0;
+pub const MAX_RADIUS: f32 = 1000.0;
+
+// steps when looking for the edge of the shadow, f32 runs out before this does
+const EDGE_STEPS: u32 = 32;
+
+// the distance to the black hole at a row, the rows go up exponentially
+pub fn row_radius(row: u32) -> f32 {
+    MIN_RADIUS * (MAX_RADIUS / MIN_RADIUS).powf(row as f32 / (RADII - 1) as f32)
+}
+
+// the angle from the way to the black hole at a column, the columns start at the edge of the
+// shadow and get closer together towards it, where the rays wrap around more and more
+pub fn column_angle(column: u32, edge: f32) -> f32 {
+    let x = column as f32 / (ANGLES - 1) as f32;
+    edge + (std::f32::consts::PI - edge) * x * x
+}
+
+// a ray starting radius away from the black hole, angle away from straight at it
+// it goes through -z towards +z, turning about +y
+fn trace(radius: f32, angle: f32, u: &TracerUniforms) -> TraceResult {
+    let photon = Photon {
+        ro: vec3(0.0, 0.0, -radius),
+        rd: vec3(angle.sin(), 0.0, angle.cos()),
+    };
+    cpu_tracer::trace_background(photon, u)
+}
+
+// the biggest angle that still falls in, 0 if nothing does
+// everything closer to straight at the black hole falls in too
+fn shadow_edge(radius: f32, u: &TracerUniforms) -> f32 {
+    let falls_in = |angle| matches!(trace(radius, angle, u), TraceResult::Blackout { .. });
+    if !falls_in(0.0) {
+        return 0.0;
+    }
+    let mut inside = 0.0;
+    let mut outside = std::f32::consts::PI;
+    for _ in 0..EDGE_STEPS {
+        let angle = 0.5 * (inside + outside);
+        if falls_in(angle) {
+            inside = angle;
+        } else {
+            outside = angle;
+        }
+    }
+    inside
+}
+
+// one row of texels, each is
+// the cos and sin of how far the ray turned, the edge of the shadow, and 1 if it escaped
+fn build_row(row: u32, u: &TracerUniforms) -> Vec<[f32; 4]> {
+    let radius = row_radius(row);
+    let edge = shadow_edge(radius, u);
+    (0..ANGLES)
+        .map(|column| {
+            let angle = column_angle(column, edge);
+            let rd = vec3(angle.sin(), 0.0, angle.cos());
+            // rd turned a quarter about +y
+            let perpendicular = Vec3::Y.cross(rd);
+            match trace(radius, angle, u) {
+                TraceResult::Escaped { direction, .. } => {
+                    [rd.dot(direction), perpendicular.dot(direction), edge, 1.0]
+                }
+                // it can still fall in past the edge when it runs out of iterations near it
+                _ => [1.0, 0.0, edge, 0.0],
+            }
+        })
+        .collect()
+}
+
+// only what the table depends on, the rest is the same for every table
+// so that tables built with the same of these compare equal
+fn table_uniforms(u: &TracerUniforms) -> TracerUniforms {
+    TracerUniforms {
+        rs: u.rs,
+        log_tolerance: u.log_tolerance,
+        blackout_eh: u.blackout_eh,
+        max_dist: u.max_dist,
+        distortion_power: u.distortion_power,
+        kerr: false,
+        integrator: u.integrator,
+        ..Default::default()
+    }
+}
+
+// the whole table, RADII rows of ANGLES texels
+// the rows are split between threads, it's a few hundred thousand rays
+// none if one of them panicked
+pub fn build(u: &TracerUniforms) -> Option<Vec<[f32; 4]>> {
+    let u = table_uniforms(u);
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            Some((0..RADII).flat_map(|row| build_row(row, &u)).collect())
+        } else {
+            let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+            let rows_per_thread = (RADII as usize).div_ceil(threads) as u32;
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = (0..RADII)
+                    .step_by(rows_per_thread as usize)
+                    .map(|start| {
+                        let end = (start + rows_per_thread).min(RADII);
+                        let u = &u;
+                        scope.spawn(move || {
+                            (start..end).flat_map(|row| build_row(row, u)).collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+                let rows: Result<Vec<_>, _> =
+                    handles.into_iter().map(|handle| handle.join()).collect();
+                Some(rows.ok()?.concat())
+            })
+        }
+    }
+}
+
+// the orbit table, for finding where rays cross the disk and pass the markers
+// with x = k / r, where k is DISTORTION_POWER * RS, every orbit follows x'' + x = 1.5 x^2 with
+// x' the derivative by the angle around its plane, so (x')^2 + x^2 - x^3 is a constant c
+// that and where along its orbit a ray starts are all that's needed to find it at any angle,
+// and none of it depends on the uniforms, so the table is only built once
+// rows with a c under CRITICAL_C are orbits that come in to a periapsis and go back out,
+// the rows after them are orbits that fall in
+// each texel is x at an angle, normalised by the x at the end of the orbit, the angle at an x,
+// normalised by the angle at the end, then that angle and x, which are the same across a row
+// the angles are from where it came in from infinity, to the periapsis or to ORBIT_MAX_X
+
+// has to match the ORBIT_TABLE_ constants in black_hole_maybe.wgsl
+pub const ORBIT_ROWS: u32 = 256;
+pub const ORBIT_COLUMNS: u32 = 512;
+// how close to the photon sphere the periapses of the rows of orbits that come back out go,
+// as a fraction of its x
+pub const ORBIT_MIN_PERIAPSIS_GAP: f32 = 1e-3;
+// how close to the critical orbit the rows of orbits that fall in go, as a fraction of
+// CRITICAL_C, and how far past it
+pub const ORBIT_MIN_GAP: f32 = 1e-6;
+pub const ORBIT_MAX_GAP: f32 = 1e6;
+// orbits falling in are only followed this far, anything that gets there is well past the horizon
+pub const ORBIT_MAX_X: f32 = 4.0;
+
+// orbits with this c only get as far in as the photon sphere, at x = 2 / 3
+const CRITICAL_C: f64 = 4.0 / 27.0;
+// steps in the integrals along each row
+const ORBIT_STEPS: usize = 4096;
+
+// c for a row
+// the first half of the rows have periapses exponentially closer to the photon sphere,
+// which goes evenly out to straight lines, where the periapsis goes as 1 / the impact parameter
+// the second half get exponentially further from CRITICAL_C above it
+pub fn orbit_row_c(row: u32) -> f64 {
+    if row < ORBIT_ROWS {
+        let t = row as f64 / (ORBIT_ROWS - 1) as f64;
+        // the last row would be c = 0, a straight line through infinity, this is close enough
+        let gap = (ORBIT_MIN_PERIAPSIS_GAP as f64)
+            .powf(1.0 - t)
+            .min(1.0 - 1e-6);
+        let periapsis = 2.0 / 3.0 * (1.0 - gap);
+        periapsis * periapsis * (1.0 - periapsis)
+    } else {
+        let min_gap = ORBIT_MIN_GAP as f64;
+        let t = (row - ORBIT_ROWS) as f64 / (ORBIT_ROWS - 1) as f64;
+        let gap = min_gap * (ORBIT_MAX_GAP as f64 / min_gap).powf(t);
+        CRITICAL_C * (1.0 + gap)
+    }
+}
+
+// how an orbit is integrated, over s from 0 where it comes in from infinity to 1 at its end
+// turning through dx / sqrt(c - x^2 + x^3) each step, which the substitutions keep finite
+enum Substitution {
+    // x = periapsis * (1 - (1 - s)^2), the roots are the ones of x^3 - x^2 + c
+    Periapsis {
+        periapsis: f64,
+        far_root: f64,
+        negative_root: f64,
+    },
+    // x = 2 / 3 + gap * sinh(t), with t going evenly from x = 0 to ORBIT_MAX_X
+    // so the steps are small where it goes slowly past the photon sphere
+    PhotonSphere {
+        gap: f64,
+        start: f64,
+        end: f64,
+    },
+}
+
+impl Substitution {
+    fn new(c: f64) -> Self {
+        if c < CRITICAL_C {
+            let theta = (1.0 - 13.5 * c).acos();
+            let root =
+                |k: f64| 1.0 / 3.0 + 2.0 / 3.0 * ((theta - std::f64::consts::TAU * k) / 3.0).cos();
+            Self::Periapsis {
+                periapsis: root(1.0),
+                far_root: root(0.0),
+                negative_root: root(2.0),
+            }
+        } else {
+            let gap = (c - CRITICAL_C).sqrt();
+            Self::PhotonSphere {
+                gap,
+                start: (-2.0 / 3.0 / gap).asinh(),
+                end: ((ORBIT_MAX_X as f64 - 2.0 / 3.0) / gap).asinh(),
+            }
+        }
+    }
+
+    fn x(&self, s: f64) -> f64 {
+        match *self {
+            Self::Periapsis { periapsis, .. } => periapsis * (1.0 - (1.0 - s) * (1.0 - s)),
+            Self::PhotonSphere { gap, start, end } => {
+                2.0 / 3.0 + gap * (start + (end - start) * s).sinh()
+            }
+        }
+    }
+
+    fn s(&self, x: f64) -> f64 {
+        match *self {
+            Self::Periapsis { periapsis, .. } => 1.0 - (1.0 - x / periapsis).max(0.0).sqrt(),
+            Self::PhotonSphere { gap, start, end } => {
+                (((x - 2.0 / 3.0) / gap).asinh() - start) / (end - start)
+            }
+        }
+    }
+
+    // the angle turned through per s
+    fn angle_rate(&self, s: f64) -> f64 {
+        let x = self.x(s);
+        match *self {
+            // c - x^2 + x^3 = periapsis * (1 - s)^2 * (far_root - x) * (x - negative_root)
+            Self::Periapsis {
+                periapsis,
+                far_root,
+                negative_root,
+            } => 2.0 * periapsis.sqrt() / ((far_root - x) * (x - negative_root)).sqrt(),
+            // c - x^2 + x^3 = gap^2 * (sinh(t)^2 * (x + 1 / 3) + 1)
+            Self::PhotonSphere { start, end, .. } => {
+                let t = start + (end - start) * s;
+                (end - start) * t.cosh() / (t.sinh().powi(2) * (x + 1.0 / 3.0) + 1.0).sqrt()
+            }
+        }
+    }
+}
+
+// a row of the orbit table, see ORBIT_ROWS
+fn build_orbit_row(row: u32) -> Vec<[f32; 4]> {
+    let substitution = Substitution::new(orbit_row_c(row));
+    // the angle from infinity at each step, with Simpson's rule on each one
+    let step = 1.0 / ORBIT_STEPS as f64;
+    let mut angles = Vec::with_capacity(ORBIT_STEPS + 1);
+    angles.push(0.0);
+    for i in 0..ORBIT_STEPS {
+        let s = i as f64 * step;
+        let turned = step / 6.0
+            * (substitution.angle_rate(s)
+                + 4.0 * substitution.angle_rate(s + 0.5 * step)
+                + substitution.angle_rate(s + step));
+        angles.push(angles[i] + turned);
+    }
+    let angle_end = angles[ORBIT_STEPS];
+    let x_end = substitution.x(1.0);
+
+    let angle_at = |s: f64| {
+        let position = s.clamp(0.0, 1.0) * ORBIT_STEPS as f64;
+        let i = (position as usize).min(ORBIT_STEPS - 1);
+        angles[i] + (angles[i + 1] - angles[i]) * (position - i as f64)
+    };
+    let s_at = |angle: f64| {
+        let i = angles
+            .partition_point(|&turned| turned < angle)
+            .clamp(1, ORBIT_STEPS);
+        let f = (angle - angles[i - 1]) / (angles[i] - angles[i - 1]);
+        ((i - 1) as f64 + f.clamp(0.0, 1.0)) * step
+    };
+    (0..ORBIT_COLUMNS)
+        .map(|column| {
+            let s = column as f64 / (ORBIT_COLUMNS - 1) as f64;
+            let x = substitution.x(s_at(angle_end * s));
+            // the xs get closer together towards the end, where the angle changes fastest
+            let angle = angle_at(substitution.s(x_end * (1.0 - (1.0 - s) * (1.0 - s))));
+            [
+                (x / x_end) as f32,
+                (angle / angle_end) as f32,
+                angle_end as f32,
+                x_end as f32,
+            ]
+        })
+        .collect()
+}
+
+pub fn build_orbit_table() -> Vec<[f32; 4]> {
+    (0..2 * ORBIT_ROWS).flat_map(build_orbit_row).collect()
+}
+
+// a whole texture of rgba32float texels
+fn write_texture(queue: &wgpu::Queue, texture: &wgpu::Texture, texels: &[[f32; 4]]) {
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        bytemuck::cast_slice(texels),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(texture.width() * std::mem::size_of::<[f32; 4]>() as u32),
+            rows_per_image: Some(texture.height()),
+        },
+        texture.size(),
+    );
+}
+
+pub struct DeflectionLut {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    // the orbit table never changes, so only the view is kept
+    pub orbit_view: wgpu::TextureView,
+    // none for the flat space table it starts with
+    built_with: Option<TracerUniforms>,
+    // the last uniforms update was called with
+    wanted: Option<TracerUniforms>,
+    // the table being built on another thread, the texture keeps the old one until it's done
+    #[cfg(not(target_arch = "wasm32"))]
+    building: Option<(TracerUniforms, mpsc::Receiver<Vec<[f32; 4]>>)>,
+}
+
+impl DeflectionLut {
+    // starts out as the table for flat space, where nothing turns or falls in
+    // the orbit table is built here, it's the same for every black hole
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("deflection lookup table"),
+            size: wgpu::Extent3d {
+                width: ANGLES,
+                height: RADII,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // float32 can't be filtered everywhere, the shader interpolates it itself
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let orbit_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("orbit lookup table"),
+            size: wgpu::Extent3d {
+                width: ORBIT_COLUMNS,
+                height: 2 * ORBIT_ROWS,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        write_texture(queue, &orbit_texture, &build_orbit_table());
+        let orbit_view = orbit_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let lut = Self {
+            texture,
+            view,
+            orbit_view,
+            built_with: None,
+            wanted: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            building: None,
+        };
+        lut.write(
+            queue,
+            &vec![[1.0, 0.0, 0.0, 1.0]; (RADII * ANGLES) as usize],
+        );
+        lut
+    }
+
+    fn write(&self, queue: &wgpu::Queue, texels: &[[f32; 4]]) {
+        write_texture(queue, &self.texture, texels);
+    }
+
+    // asks for the table to be rebuilt if anything it depends on changed since it was last built
+    // kerr doesn't matter, the shader doesn't use the table with it
+    // it's built on another thread, poll picks it up, and if this is called again before it's
+    // done only the last uniforms are built next
+    // there are no threads on the web, so it's built straight away there
+    pub fn update(&mut self, queue: &wgpu::Queue, u: &TracerUniforms) {
+        self.wanted = Some(table_uniforms(u));
+        self.start_build(queue);
+    }
+
+    fn start_build(&mut self, queue: &wgpu::Queue) {
+        let Some(u) = self.wanted else {
+            return;
+        };
+        if self.built_with == Some(u) {
+            return;
+        }
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                println!("building the deflection lookup table");
+                match build(&u) {
+                    Some(texels) => self.write(queue, &texels),
+                    None => log::error!("building the deflection lookup table failed, keeping the last one"),
+                }
+                // it would only fail again
+                self.built_with = Some(u);
+            } else {
+                // only the web uploads it here
+                let _ = queue;
+                if self.building.is_some() {
+                    return;
+                }
+                println!("building the deflection lookup table");
+                let (sender, receiver) = mpsc::channel();
+                std::thread::spawn(move || {
+                    // the receiver is gone if the scene was dropped, then nobody needs it
+                    // and if it failed the sender is dropped, which poll picks up
+                    if let Some(texels) = build(&u) {
+                        let _ = sender.send(texels);
+                    }
+                });
+                self.building = Some((u, receiver));
+            }
+        }
+    }
+
+    // uploads the table if it's finished and starts on the next one if it's been asked for
+    // returns whether the texture changed
+    pub fn poll(&mut self, queue: &wgpu::Queue) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some((u, receiver)) = &self.building {
+            let texels = match receiver.try_recv() {
+                Ok(texels) => texels,
+                Err(mpsc::TryRecvError::Empty) => return false,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.build_failed();
+                    return false;
+                }
+            };
+            self.finish_build(queue, *u, &texels);
+            return true;
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = queue;
+        false
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn finish_build(&mut self, queue: &wgpu::Queue, u: TracerUniforms, texels: &[[f32; 4]]) {
+        println!("built the deflection lookup table");
+        self.built_with = Some(u);
+        self.building = None;
+        self.write(queue, texels);
+        self.start_build(queue);
+    }
+
+    // the texture keeps the last table, and these uniforms aren't tried again until update asks
+    // for something else and then them again, they'd only fail again
+    #[cfg(not(target_arch = "wasm32"))]
+    fn build_failed(&mut self) {
+        log::error!("building the deflection lookup table failed, keeping the last one");
+        if let Some((u, _)) = self.building.take() {
+            self.built_with = Some(u);
+        }
+    }
+
+    // blocks until the table for the last update is in the texture
+    pub fn wait(&mut self, queue: &wgpu::Queue) {
+        #[cfg(not(target_arch = "wasm32"))]
+        while let Some((u, receiver)) = &self.building {
+            let Ok(texels) = receiver.recv() else {
+                self.build_failed();
+                return;
+            };
+            self.finish_build(queue, *u, &texels);
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = queue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu_tracer::{tests::shader_const, Integrator};
+
+    // where the photons start, with k = 1
+    const START: f32 = 1000.0;
+
+    fn uniforms() -> TracerUniforms {
+        TracerUniforms {
+            max_dist: 1e5,
+            integrator: Integrator::Rk45,
+            log_tolerance: -7.0,
+            ..Default::default()
+        }
+    }
+
+    // linear along a row, like orbit_table_load in the shader
+    fn lerp_row(row: &[[f32; 4]], column: f64, channel: usize) -> f64 {
+        let column = column.clamp(0.0, (ORBIT_COLUMNS - 1) as f64);
+        let low = (column as usize).min(ORBIT_COLUMNS as usize - 2);
+        let f = column - low as f64;
+        row[low][channel] as f64 * (1.0 - f) + row[low + 1][channel] as f64 * f
+    }
+
+    // how far an orbit has turned from infinity by the time it gets to x, like orbit_angle
+    fn angle_at(row: &[[f32; 4]], x: f64) -> f64 {
+        let [_, _, angle_end, x_end] = row[0];
+        let s = 1.0 - (1.0 - x / x_end as f64).max(0.0).sqrt();
+        angle_end as f64 * lerp_row(row, s * (ORBIT_COLUMNS - 1) as f64, 1)
+    }
+
+    // a photon at START with the c of the row, coming in
+    // and the unit vectors of its plane, it starts at the first and heads towards the second
+    fn photon(row: u32) -> (Photon, Vec3, Vec3) {
+        let c = orbit_row_c(row);
+        let x = 1.0 / START as f64;
+        let angle = (1.0 / (c + x * x * x).sqrt() / START as f64).asin() as f32;
+        let photon = Photon {
+            ro: vec3(0.0, 0.0, -START),
+            rd: vec3(angle.sin(), 0.0, angle.cos()),
+        };
+        (photon, vec3(0.0, 0.0, -1.0), vec3(1.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn constants_match_the_shader() {
+        let shader = include_str!("black_hole_maybe.wgsl");
+        assert_eq!(
+            shader_const(shader, "DEFLECTION_LUT_MIN_RADIUS"),
+            MIN_RADIUS
+        );
+        assert_eq!(
+            shader_const(shader, "DEFLECTION_LUT_MAX_RADIUS"),
+            MAX_RADIUS
+        );
+        assert_eq!(
+            shader_const(shader, "ORBIT_TABLE_MIN_PERIAPSIS_GAP"),
+            ORBIT_MIN_PERIAPSIS_GAP
+        );
+        assert_eq!(shader_const(shader, "ORBIT_TABLE_MIN_GAP"), ORBIT_MIN_GAP);
+        assert_eq!(shader_const(shader, "ORBIT_TABLE_MAX_GAP"), ORBIT_MAX_GAP);
+        assert_eq!(shader_const(shader, "ORBIT_TABLE_MAX_X"), ORBIT_MAX_X);
+    }
+
+    #[test]
+    fn straight_lines_turn_half_way_round() {
+        let row = build_orbit_row(ORBIT_ROWS - 1);
+        assert!((row[0][2] - std::f32::consts::FRAC_PI_2).abs() < 1e-4);
+        for column in [0, 100, 300, ORBIT_COLUMNS as usize - 1] {
+            let s = column as f32 / (ORBIT_COLUMNS - 1) as f32;
+            let x = (s * std::f32::consts::FRAC_PI_2).sin();
+            assert!((row[column][0] - x).abs() < 1e-4, "{:?}", row[column]);
+        }
+    }
+
+    #[test]
+    fn orbits_turn_as_far_as_traced_rays() {
+        let u = uniforms();
+        // closer to the critical orbit than this f32 can't aim the traced ray well enough
+        for row in [130, 160, 200, 250] {
+            let texels = build_orbit_row(row);
+            let (photon, e1, e2) = photon(row);
+            // in and back out to infinity, where it's heading straight away
+            let turned = 2.0 * texels[0][2] as f64 - angle_at(&texels, 1.0 / START as f64);
+            let expected = e1 * (turned.cos() as f32) + e2 * (turned.sin() as f32);
+            match cpu_tracer::trace_background(photon, &u) {
+                TraceResult::Escaped { direction, .. } => assert!(
+                    direction.angle_between(expected) < 2e-3,
+                    "row {}: turned {} but the traced ray went {:?} rather than {:?}",
+                    row,
+                    turned,
+                    direction,
+                    expected
+                ),
+                result => panic!("row {}: {:?}", row, result),
+            }
+        }
+    }
+
+    #[test]
+    fn orbits_fall_in_where_traced_rays_do() {
+        let u = uniforms();
+        for row in [ORBIT_ROWS + 60, ORBIT_ROWS + 128, ORBIT_ROWS + 200] {
+            let texels = build_orbit_row(row);
+            let (photon, e1, e2) = photon(row);
+            match cpu_tracer::trace_background(photon, &u) {
+                TraceResult::Blackout { position, .. } => {
+                    // the horizon is at r = 1
+                    let turned = angle_at(&texels, 1.0 / position.length() as f64)
+                        - angle_at(&texels, 1.0 / START as f64);
+                    let traced = position.dot(e2).atan2(position.dot(e1)) as f64;
+                    let difference = (turned - traced).rem_euclid(std::f64::consts::TAU);
+                    assert!(
+                        difference.min(std::f64::consts::TAU - difference) < 2e-3,
+                        "row {}: turned {} rather than {}",
+                        row,
+                        turned,
+                        traced
+                    );
+                }
+                result => panic!("row {}: {:?}", row, result),
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+// dynamic resolution, for keeping the frame rate up close to the black hole
+// the scene renders at a fraction of the output size and gets upscaled before the
+// rest of post processing, and that fraction follows how long the frames take
+
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{sync::mpsc, thread};
+
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{
+    fullscreen_pass::FullscreenPass,
+    render_graph::{RenderGraph, Target, TextureHandle, TextureSize},
+    time_replacement::{Duration, Instant},
+};
+
+// the frame rate aimed for when there's no maximum frame rate
+pub const DEFAULT_TARGET_FRAME_RATE: f32 = 60.0;
+
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+// the scale is rounded to this, so it doesn't reallocate the scene's textures every frame
+const RENDER_SCALE_STEP: f32 = 1.0 / 16.0;
+// how far the frame time can be from the target before the scale changes, as a fraction
+const TOLERANCE: f32 = 0.1;
+// frames to average after a change before the next one
+const SETTLE_FRAMES: u32 = 30;
+// how much each frame moves the average frame time
+const SMOOTHING: f32 = 0.1;
+
+// how long frames take to get through the gpu, without the render loop waiting for them
+// each submission gets a callback noting when it's done, and the time is picked up by a
+// later frame. natively a thread waits for the gpu so the callbacks run as soon as it's done,
+// on the web the browser calls them
+pub struct FrameTimer {
+    // from the start of the last frame that finished to it finishing
+    finished: Arc<Mutex<Option<Duration>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    submissions: mpsc::Sender<wgpu::SubmissionIndex>,
+}
+
+impl FrameTimer {
+    pub fn new(device: Arc<wgpu::Device>) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let submissions = {
+            let (sender, receiver) = mpsc::channel();
+            // stops when the timer is dropped
+            thread::spawn(move || {
+                for index in receiver {
+                    device.poll(wgpu::Maintain::WaitForSubmissionIndex(index));
+                }
+            });
+            sender
+        };
+        #[cfg(target_arch = "wasm32")]
+        let _ = device;
+        Self {
+            finished: Arc::new(Mutex::new(None)),
+            #[cfg(not(target_arch = "wasm32"))]
+            submissions,
+        }
+    }
+
+    // has to be called right after a frame is submitted, with when it started
+    pub fn submitted(
+        &self,
+        queue: &wgpu::Queue,
+        index: wgpu::SubmissionIndex,
+        frame_start: Instant,
+    ) {
+        let finished = self.finished.clone();
+        queue.on_submitted_work_done(move || {
+            *finished.lock().unwrap() = Some(frame_start.elapsed());
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = self.submissions.send(index);
+        #[cfg(target_arch = "wasm32")]
+        let _ = index;
+    }
+
+    // none if no frame has finished since the last call
+    pub fn take(&self) -> Option<Duration> {
+        self.finished.lock().unwrap().take()
+    }
+}
+
+pub struct DynamicResolution {
+    // changing this changes the graph, PostProcessing rebuilds it
+    enabled: bool,
+    render_scale: f32,
+    // seconds, none right after a change
+    average_frame_time: Option<f32>,
+    frames_since_change: u32,
+}
+
+impl Default for DynamicResolution {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            render_scale: 1.0,
+            average_frame_time: None,
+            frames_since_change: 0,
+        }
+    }
+}
+
+impl DynamicResolution {
+    // declares input upscaled to the output size
+    // returns the texture holding it
+    pub fn add_to_graph(
+        &self,
+        graph: &mut RenderGraph,
+        device: &wgpu::Device,
+        label: &str,
+        input: TextureHandle,
+    ) -> TextureHandle {
+        let format = graph.format(input);
+        let upscaled = graph.add_texture(
+            &format!("{} upscaled texture", label),
+            TextureSize::Full,
+            format,
+        );
+        let pass_label = format!("{} upscaling", label);
+        graph.add_pass(
+            &pass_label,
+            &[input],
+            &[Target::Texture(upscaled)],
+            FullscreenPass::new(
+                device,
+                &pass_label,
+                include_shader!("src/upscale.wgsl"),
+                1,
+                vec![],
+                format,
+            ),
+        );
+        upscaled
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // turning it off goes back to the full resolution
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.render_scale = 1.0;
+        self.average_frame_time = None;
+        self.frames_since_change = 0;
+        println!("dynamic resolution: {}", if enabled { "on" } else { "off" });
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    // has to be called with how long each frame took to render, as they finish
+    // returns the new render scale if it changed
+    pub fn update(&mut self, frame_time: Duration, target_frame_time: Duration) -> Option<f32> {
+        if !self.enabled {
+            return None;
+        }
+        let frame_time = frame_time.as_secs_f32();
+        let average_frame_time = match self.average_frame_time {
+            Some(average) => average + (frame_time - average) * SMOOTHING,
+            None => frame_time,
+        };
+        self.average_frame_time = Some(average_frame_time);
+        self.frames_since_change += 1;
+        if self.frames_since_change < SETTLE_FRAMES {
+            return None;
+        }
+
+        let target_frame_time = target_frame_time.as_secs_f32();
+        if (average_frame_time / target_frame_time - 1.0).abs() < TOLERANCE {
+            return None;
+        }
+        // the frame time goes about with the number of pixels, the scale squared
+        let wanted = self.render_scale * (target_frame_time / average_frame_time).sqrt();
+        let render_scale =
+            ((wanted / RENDER_SCALE_STEP).round() * RENDER_SCALE_STEP).clamp(MIN_RENDER_SCALE, 1.0);
+        if render_scale == self.render_scale {
+            return None;
+        }
+        self.render_scale = render_scale;
+        self.average_frame_time = None;
+        self.frames_since_change = 0;
+        Some(render_scale)
+    }
+
+    // h toggles it, which needs the graph rebuilding
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::KeyH),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                self.set_enabled(!self.enabled);
+                true
+            }
+            _ => false,
+        }
+    }
+}
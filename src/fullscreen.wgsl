@@ -0,0 +1,11 @@
+// what screen_triangle.wgsl passes to the fragment shaders of fullscreen passes
+
+struct VertexOutput {
+    // Mark output position as invariant so it's safe to use it with depth test Equal.
+    // Without @invariant, different usages in different render pipelines might optimize differently,
+    // causing slightly different results.
+    @invariant @builtin(position)
+    position: vec4f,
+    @location(0)
+    texcoord: vec2f,
+};
@@ -0,0 +1,270 @@
+// a pass that draws screen_triangle.wgsl with a fragment shader over its whole output
+// the fragment shader's entry point is main, and its bindings in group 0 are
+//   input i: texture_2d<f32> at 2 * i and its sampler at 2 * i + 1
+//   uniform j: after the inputs, at 2 * inputs + j
+// in the render graph the graph binds the inputs and rebinds them on resize,
+// outside it call bind whenever the input views change
+// while hot reloading the pipeline is built again when either shader changes
+
+use std::sync::Arc;
+
+use crate::{
+    render_graph::GraphPass,
+    shader::{self, Shader, Sources},
+};
+
+const SCREEN_TRIANGLE_SHADER: Shader = include_shader!("src/screen_triangle.wgsl");
+
+pub struct FullscreenPass {
+    pub label: String,
+
+    pub texture_sampler: wgpu::Sampler,
+
+    // shared with whoever owns the settings, so they can be written without the pass
+    pub uniform_buffers: Vec<Arc<wgpu::Buffer>>,
+
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    // None until the inputs are bound
+    pub bind_group: Option<wgpu::BindGroup>,
+    pub render_pipeline: wgpu::RenderPipeline,
+
+    inputs: usize,
+    // what the pipeline is built from
+    fragment: Shader,
+    pipeline_layout: wgpu::PipelineLayout,
+    output_format: wgpu::TextureFormat,
+}
+
+impl FullscreenPass {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        fragment: Shader,
+        inputs: usize,
+        uniform_buffers: Vec<Arc<wgpu::Buffer>>,
+        output_format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(&format!("{} sampler", label)),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let mut entries = Vec::new();
+        for input in 0..inputs as u32 {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 * input,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 * input + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            });
+        }
+        for uniform in 0..uniform_buffers.len() as u32 {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 * inputs as u32 + uniform,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            });
+        }
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{} bind group layout", label)),
+            entries: &entries,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&format!("{} pipeline layout", label)),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = shader::build(device, label, |sources| {
+            Self::create_render_pipeline(
+                device,
+                label,
+                &pipeline_layout,
+                fragment,
+                output_format,
+                sources,
+            )
+        });
+
+        Self {
+            label: label.to_string(),
+
+            texture_sampler,
+
+            uniform_buffers,
+
+            bind_group_layout,
+            bind_group: None,
+            render_pipeline,
+
+            inputs,
+            fragment,
+            pipeline_layout,
+            output_format,
+        }
+    }
+
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        label: &str,
+        pipeline_layout: &wgpu::PipelineLayout,
+        fragment: Shader,
+        output_format: wgpu::TextureFormat,
+        sources: Sources,
+    ) -> wgpu::RenderPipeline {
+        let screen_triangle_shader_module =
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(SCREEN_TRIANGLE_SHADER.path),
+                source: wgpu::ShaderSource::Wgsl(sources.get(SCREEN_TRIANGLE_SHADER)),
+            });
+        let fragment_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(fragment.path),
+            source: wgpu::ShaderSource::Wgsl(sources.get(fragment)),
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("{} render pipeline", label)),
+            layout: Some(pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &screen_triangle_shader_module,
+                entry_point: "main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fragment_shader_module,
+                entry_point: "main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: output_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+}
+
+impl GraphPass for FullscreenPass {
+    fn bind(&mut self, device: &wgpu::Device, inputs: &[&wgpu::TextureView]) {
+        assert_eq!(
+            inputs.len(),
+            self.inputs,
+            "{} takes {} inputs",
+            self.label,
+            self.inputs
+        );
+        let mut entries = Vec::new();
+        for (input, view) in inputs.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 * input as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 * input as u32 + 1,
+                resource: wgpu::BindingResource::Sampler(&self.texture_sampler),
+            });
+        }
+        for (uniform, buffer) in self.uniform_buffers.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: (2 * self.inputs + uniform) as u32,
+                resource: buffer.as_entire_binding(),
+            });
+        }
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} bind group", self.label)),
+            layout: &self.bind_group_layout,
+            entries: &entries,
+        }));
+    }
+
+    fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        outputs: &[&wgpu::TextureView],
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&format!("{} render pass", self.label)),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: outputs[0],
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(
+            0,
+            self.bind_group
+                .as_ref()
+                .expect("fullscreen passes have to be bound before they render"),
+            &[],
+        );
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if !self.fragment.changed_in(changed) && !SCREEN_TRIANGLE_SHADER.changed_in(changed) {
+            return;
+        }
+        let render_pipeline = shader::rebuild(device, &self.label, |sources| {
+            Self::create_render_pipeline(
+                device,
+                &self.label,
+                &self.pipeline_layout,
+                self.fragment,
+                self.output_format,
+                sources,
+            )
+        });
+        if let Some(render_pipeline) = render_pipeline {
+            self.render_pipeline = render_pipeline;
+        }
+    }
+}
@@ -0,0 +1,196 @@
+// how long each pass takes on the gpu, from timestamp queries
+// every pass asks for its timestamp writes with its label while the frame is encoded,
+// then resolve goes at the end of the encoder and read after it's submitted
+// reading waits for the gpu to finish the frame, so it's only on when it's asked for
+
+use std::{fmt::Write, sync::mpsc, time::Duration};
+
+// two queries each
+pub const MAX_PASSES: u32 = 64;
+
+#[derive(Debug, Clone)]
+pub struct PassTiming {
+    pub label: String,
+    pub duration: Duration,
+}
+
+pub struct GpuProfiler {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    // nanoseconds per tick
+    timestamp_period: f32,
+    // the passes given timestamp writes since begin_frame, in query order
+    labels: Vec<String>,
+    // from the last frame that was read
+    timings: Vec<PassTiming>,
+}
+
+impl GpuProfiler {
+    // what the device has to be created with for new to work, if the adapter has it
+    pub fn features(adapter: &wgpu::Adapter) -> wgpu::Features {
+        adapter.features() & wgpu::Features::TIMESTAMP_QUERY
+    }
+
+    // none if the device can't do timestamp queries
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let size =
+            (2 * MAX_PASSES) as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+        Some(Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("gpu profiler query set"),
+                ty: wgpu::QueryType::Timestamp,
+                count: 2 * MAX_PASSES,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("gpu profiler resolve buffer"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("gpu profiler readback buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            timestamp_period: queue.get_timestamp_period(),
+            labels: vec![],
+            timings: vec![],
+        })
+    }
+
+    // GPU_PROFILE=1 cargo run turns it on for the window
+    pub fn from_env(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        std::env::var_os("GPU_PROFILE")?;
+        let profiler = Self::new(device, queue);
+        if profiler.is_none() {
+            log::warn!("GPU_PROFILE is set but the device can't do timestamp queries");
+        }
+        profiler
+    }
+
+    // has to be called before anything asks for timestamp writes
+    pub fn begin_frame(&mut self) {
+        self.labels.clear();
+    }
+
+    // none once MAX_PASSES have been given out this frame
+    fn next_queries(&mut self, label: &str) -> Option<(u32, u32)> {
+        let index = self.labels.len() as u32;
+        if index >= MAX_PASSES {
+            return None;
+        }
+        self.labels.push(label.to_string());
+        Some((2 * index, 2 * index + 1))
+    }
+
+    pub fn render_pass(&mut self, label: &str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let (beginning, end) = self.next_queries(label)?;
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(beginning),
+            end_of_pass_write_index: Some(end),
+        })
+    }
+
+    pub fn compute_pass(&mut self, label: &str) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        let (beginning, end) = self.next_queries(label)?;
+        Some(wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(beginning),
+            end_of_pass_write_index: Some(end),
+        })
+    }
+
+    // has to go after every pass in the frame
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        let queries = 2 * self.labels.len() as u32;
+        if queries == 0 {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..queries, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            queries as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress,
+        );
+    }
+
+    // has to be called after the encoder with resolve in it is submitted
+    // waits for the gpu, then the frame's timings are in timings
+    pub fn read(&mut self, device: &wgpu::Device) -> &[PassTiming] {
+        self.timings.clear();
+        if self.labels.is_empty() {
+            return &self.timings;
+        }
+        let size =
+            2 * self.labels.len() as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+        let buffer_slice = self.readback_buffer.slice(..size);
+        let (sender, receiver) = mpsc::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        if !matches!(receiver.recv(), Ok(Ok(()))) {
+            log::warn!("the gpu profiler's readback buffer couldn't be mapped");
+            return &self.timings;
+        }
+        {
+            let data = buffer_slice.get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            for (label, pair) in self.labels.iter().zip(timestamps.chunks_exact(2)) {
+                let ticks = pair[1].saturating_sub(pair[0]);
+                self.timings.push(PassTiming {
+                    label: label.clone(),
+                    duration: Duration::from_nanos(
+                        (ticks as f64 * self.timestamp_period as f64) as u64,
+                    ),
+                });
+            }
+        }
+        self.readback_buffer.unmap();
+        &self.timings
+    }
+
+    // the passes from the last read, in the order they ran
+    pub fn timings(&self) -> &[PassTiming] {
+        &self.timings
+    }
+
+    pub fn total(&self) -> Duration {
+        self.timings.iter().map(|timing| timing.duration).sum()
+    }
+
+    // one pass per line and the total, for logging
+    pub fn table(&self) -> String {
+        let width = self
+            .timings
+            .iter()
+            .map(|timing| timing.label.len())
+            .max()
+            .unwrap_or(0)
+            .max("total".len());
+        let mut table = String::new();
+        for timing in &self.timings {
+            let _ = writeln!(
+                table,
+                "{:<width$}  {:>8.3} ms",
+                timing.label,
+                timing.duration.as_secs_f64() * 1000.0
+            );
+        }
+        let _ = write!(
+            table,
+            "{:<width$}  {:>8.3} ms",
+            "total",
+            self.total().as_secs_f64() * 1000.0
+        );
+        table
+    }
+}
@@ -0,0 +1,44 @@
+// the scene and every intermediate texture in the bloom chain use a floating point format
+// so colours brighter than 1 aren't clipped before bloom gets to them
+// only tone mapping writes to the surface format
+
+use wgpu::{
+    Adapter, SurfaceConfiguration, TextureFormat, TextureFormatFeatureFlags, TextureUsages,
+};
+
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+// what the chain used before, for adapters that can't render to or filter HDR_FORMAT
+// (webgl2 without EXT_color_buffer_float for example)
+pub const FALLBACK_FORMAT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
+
+pub fn hdr_format(adapter: &Adapter) -> TextureFormat {
+    let features = adapter.get_texture_format_features(HDR_FORMAT);
+    let supported = features
+        .allowed_usages
+        .contains(TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING)
+        && features
+            .flags
+            .contains(TextureFormatFeatureFlags::FILTERABLE);
+    if supported {
+        HDR_FORMAT
+    } else {
+        log::warn!(
+            "{:?} isn't supported, bloom falls back to {:?} and clips at 1",
+            HDR_FORMAT,
+            FALLBACK_FORMAT
+        );
+        FALLBACK_FORMAT
+    }
+}
+
+// the surface config with the format swapped, for everything that renders before tone mapping
+pub fn hdr_config(
+    config: &SurfaceConfiguration,
+    hdr_format: TextureFormat,
+) -> SurfaceConfiguration {
+    SurfaceConfiguration {
+        format: hdr_format,
+        view_formats: vec![],
+        ..config.clone()
+    }
+}
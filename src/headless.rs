@@ -0,0 +1,327 @@
+// renders frames without a window, for ci, servers and tests
+// the scene and the post processing graph draw into an offscreen texture
+// which then gets copied back to the cpu
+
+use std::{iter, path::Path, time::Duration};
+
+use anyhow::{anyhow, bail, Context};
+use image::{DynamicImage, Rgba32FImage, RgbaImage};
+use wgpu::{Device, Instance, Queue, SurfaceConfiguration};
+
+use crate::{
+    bloom::BloomSettings,
+    gpu_profiler::GpuProfiler,
+    hdr::{hdr_config, hdr_format, HDR_FORMAT},
+    post_processing::PostProcessing,
+    scene::{Scene, SceneBackend},
+    screenshot::{self, TextureReadback},
+};
+
+// what tone mapping writes, everything before it is hdr where the adapter allows
+pub const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+pub struct HeadlessRenderer {
+    pub device: Device,
+    pub queue: Queue,
+    // never given to a surface, the passes just read the size and format out of it
+    pub config: SurfaceConfiguration,
+
+    pub scene: Scene,
+    pub post_processing: PostProcessing,
+
+    pub output_texture: wgpu::Texture,
+    pub output_texture_view: wgpu::TextureView,
+
+    // none unless set_profiling turned it on, the timings are from the last render
+    pub profiler: Option<GpuProfiler>,
+}
+
+impl HeadlessRenderer {
+    // uses a real gpu if there is one and falls back to a software adapter if there isn't
+    // force_fallback_adapter goes straight to the software adapter
+    pub async fn new(
+        width: u32,
+        height: u32,
+        bloom_settings: BloomSettings,
+        backend: SceneBackend,
+        force_fallback_adapter: bool,
+    ) -> anyhow::Result<Self> {
+        let width = width.max(1);
+        let height = height.max(1);
+
+        let instance = Instance::default();
+
+        let mut adapter = None;
+        if !force_fallback_adapter {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    force_fallback_adapter: false,
+                    compatible_surface: None,
+                })
+                .await;
+        }
+        if adapter.is_none() {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    force_fallback_adapter: true,
+                    compatible_surface: None,
+                })
+                .await;
+        }
+        let adapter = adapter.ok_or(anyhow!("Failed to find an appropriate adapter"))?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("headless device"),
+                    required_features: GpuProfiler::features(&adapter),
+                    required_limits: backend.required_limits(&adapter),
+                    memory_hints: wgpu::MemoryHints::MemoryUsage,
+                },
+                None,
+            )
+            .await
+            .context("Failed to create device")?;
+
+        let config = SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: HEADLESS_FORMAT,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        let hdr_format = hdr_format(&adapter);
+        let scene = Scene::new(
+            &device,
+            &queue,
+            &hdr_config(&config, hdr_format),
+            true,
+            backend,
+        );
+        let post_processing = PostProcessing::new(
+            &device,
+            &config,
+            hdr_format,
+            scene.backend().output_usage(),
+            bloom_settings,
+        );
+
+        let (output_texture, output_texture_view) = Self::create_output_texture(&device, &config);
+
+        Ok(Self {
+            device,
+            queue,
+            config,
+
+            scene,
+            post_processing,
+
+            output_texture,
+            output_texture_view,
+
+            profiler: None,
+        })
+    }
+
+    // errors if the adapter can't do timestamp queries
+    pub fn set_profiling(&mut self, enabled: bool) -> anyhow::Result<()> {
+        self.profiler = if enabled {
+            Some(
+                GpuProfiler::new(&self.device, &self.queue)
+                    .ok_or(anyhow!("This adapter can't do timestamp queries"))?,
+            )
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    fn create_output_texture(
+        device: &Device,
+        config: &SurfaceConfiguration,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let output_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("headless output texture"),
+            mip_level_count: 1,
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            format: config.format,
+            dimension: wgpu::TextureDimension::D2,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            sample_count: 1,
+            view_formats: &[],
+        });
+        let output_texture_view = output_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (output_texture, output_texture_view)
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.config.width = width.max(1);
+        self.config.height = height.max(1);
+        self.scene.resize(&self.device, &self.queue, &self.config);
+        self.post_processing
+            .resize(&self.device, self.config.width, self.config.height);
+        (self.output_texture, self.output_texture_view) =
+            Self::create_output_texture(&self.device, &self.config);
+    }
+
+    // moves the scene on by delta_time and uploads the camera
+    // there's no input, so the camera only moves if it's changed directly
+    // which is why every update starts the progressive average over
+    pub fn update(&mut self, delta_time: Duration) {
+        self.scene.finish_deflection_lut(&self.queue);
+        self.scene.update(delta_time, None, None, &self.queue);
+        self.scene.take_changed();
+        let jitter = self
+            .post_processing
+            .next_frame(&self.queue, &self.scene.camera, true);
+        self.scene.set_jitter(&self.queue, jitter);
+    }
+
+    // renders frames into the progressive average without reading them back
+    // the next render adds one more, so n samples are accumulate(n - 1) then render
+    pub fn accumulate(&mut self, frames: u32) {
+        for _ in 0..frames {
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("headless accumulation encoder"),
+                });
+            self.encode_frame(&mut encoder);
+            self.queue.submit(iter::once(encoder.finish()));
+
+            let jitter = self.post_processing.progressive.next_frame(&self.queue, false);
+            self.scene.set_jitter(&self.queue, jitter);
+        }
+    }
+
+    fn encode_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.begin_frame();
+        }
+        self.scene.render(
+            &self.device,
+            encoder,
+            Some(self.post_processing.scene_view()),
+            Some(self.post_processing.scene_blackout_view()),
+            self.profiler.as_mut(),
+        );
+        self.post_processing
+            .render(encoder, &self.output_texture_view, self.profiler.as_mut());
+        if let Some(profiler) = &self.profiler {
+            profiler.resolve(encoder);
+        }
+    }
+
+    // renders one frame and copies texture back once it's done
+    fn render_and_read_back(
+        &mut self,
+        texture: impl Fn(&Self) -> &wgpu::Texture,
+    ) -> TextureReadback {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("headless render encoder"),
+            });
+
+        self.encode_frame(&mut encoder);
+
+        let readback = TextureReadback::new(&self.device, &mut encoder, texture(self));
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        readback
+    }
+
+    fn read_profiler(&mut self) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.read(&self.device);
+        }
+    }
+
+    // renders one frame and waits for it to come back
+    pub fn render(&mut self) -> anyhow::Result<RgbaImage> {
+        let readback = self.render_and_read_back(|renderer| &renderer.output_texture);
+        let image = readback.read(&self.device)?;
+        self.read_profiler();
+        Ok(image)
+    }
+
+    // renders one frame and waits for the image tone mapping reads to come back
+    // it's linear, with the bloom but before exposure, and isn't clipped to 1
+    // errors if the adapter couldn't do HDR_FORMAT, since that image is already clipped
+    pub fn render_hdr(&mut self) -> anyhow::Result<Rgba32FImage> {
+        if self.post_processing.hdr_format() != HDR_FORMAT {
+            bail!(
+                "This adapter can't render to {:?}, so there's no hdr image to save",
+                HDR_FORMAT
+            );
+        }
+        let readback =
+            self.render_and_read_back(|renderer| renderer.post_processing.tone_mapping_input());
+        let mut image = readback.read_hdr(&self.device)?;
+        self.read_profiler();
+        // the passes before tone mapping add up alpha along with the colour, the image is opaque
+        for pixel in image.pixels_mut() {
+            pixel.0[3] = 1.0;
+        }
+        Ok(image)
+    }
+
+    // pngs get every parameter in their text chunks, see screenshot::parameters
+    // exrs get the hdr image, see render_hdr
+    pub fn render_to_file(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if has_extension(path, "exr") {
+            let image = self.render_hdr()?;
+            return DynamicImage::ImageRgba32F(image)
+                .save(path)
+                .with_context(|| format!("Failed to save {}", path.display()));
+        }
+        let image = self.render()?;
+        save_image(
+            &image,
+            path,
+            &screenshot::parameters(&self.scene, &self.post_processing),
+        )
+    }
+}
+
+fn has_extension(path: &Path, name: &str) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(name))
+}
+
+// the format comes from the extension
+// only pngs keep the parameters
+// exrs have to come from HeadlessRenderer::render_to_file, a tone mapped image has lost its range
+pub fn save_image(
+    image: &RgbaImage,
+    path: impl AsRef<Path>,
+    parameters: &[(String, String)],
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    if has_extension(path, "png") {
+        screenshot::save_png(image, path, parameters)?;
+    } else if has_extension(path, "exr") {
+        bail!(
+            "{} is an exr, which is saved from the image before tone mapping, see HeadlessRenderer::render_to_file",
+            path.display()
+        );
+    } else {
+        image
+            .save(path)
+            .with_context(|| format!("Failed to save {}", path.display()))?;
+    }
+    Ok(())
+}
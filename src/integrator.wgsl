@@ -0,0 +1,300 @@
+// the photon integrators, fixed step rk4, adaptive rk45 and the binet equation
+// for schwarzschild and kerr, picked in get_col with u.INTEGRATOR
+// the mass and spin come from u, so it goes in a shader with the scene's uniforms
+// cpu_tracer.rs has a copy of it
+
+fn rd_derivative(ro: vec3<f32>, h2: f32) -> vec3<f32> {
+    return u.DISTORTION_POWER * u.RS * -1.5 * h2 * ro / pow(dot(ro, ro), 2.5);
+}
+
+struct Photon {
+    ro: vec3<f32>,
+    rd: vec3<f32>,
+}
+
+fn get_delta_photon_rk4(photon: Photon, delta_time: f32, h2: f32) -> Photon {
+    let ro_k1 = delta_time * photon.rd;
+    let rd_k1 = delta_time * rd_derivative(photon.ro, h2);
+    
+    let ro_k2 = delta_time * (photon.rd + 0.5 * rd_k1);
+    let rd_k2 = delta_time * rd_derivative(photon.ro + 0.5 * ro_k1, h2);
+    
+    let ro_k3 = delta_time * (photon.rd + 0.5 * rd_k2);
+    let rd_k3 = delta_time * rd_derivative(photon.ro + 0.5 * ro_k2, h2);
+    
+    let ro_k4 = delta_time * (photon.rd + rd_k3);
+    let rd_k4 = delta_time * rd_derivative(photon.ro + ro_k3, h2);
+
+    let delta_ro = (ro_k1 + 2.0 * ro_k2 + 2.0 * ro_k3 + ro_k4) / 6.0;
+    let delta_rd = (rd_k1 + 2.0 * rd_k2 + 2.0 * rd_k3 + rd_k4) / 6.0;
+
+    return Photon(delta_ro, delta_rd);
+}
+
+// kerr
+// the metric is written in kerr-schild form with the spin axis along +y
+// g^{mu nu} = eta^{mu nu} - f l^mu l^nu
+// so everything stays cartesian and the horizon is just another surface the ray can cross
+// in kerr mode photon.ro is the position and photon.rd is the covariant spatial momentum p_i
+// with the energy fixed at p_t = -1
+// the geodesic then comes from the hamiltonian H = 1/2 g^{mu nu} p_mu p_nu
+
+// M, schwarzschild mode uses it for the disk too
+fn black_hole_mass() -> f32 {
+    return 0.5 * u.DISTORTION_POWER * u.RS;
+}
+
+fn kerr_spin() -> f32 {
+    return clamp(u.SPIN, -0.999, 0.999);
+}
+
+fn kerr_a() -> f32 {
+    return kerr_spin() * black_hole_mass();
+}
+
+// the integrator sends photons forwards in time from the camera,
+// which is the real photon running backwards,
+// and running a kerr spacetime backwards is the same as spinning it the other way
+fn kerr_trace_a() -> f32 {
+    return -kerr_a();
+}
+
+// outer event horizon in boyer-lindquist r
+fn kerr_horizon_radius() -> f32 {
+    let m = black_hole_mass();
+    let a = kerr_a();
+    return m + sqrt(m * m - a * a);
+}
+
+// prograde isco in boyer-lindquist r (bardeen, press & teukolsky 1972)
+fn isco_radius(m: f32, spin: f32) -> f32 {
+    let a = abs(spin);
+    let z1 = 1.0 + pow(1.0 - a * a, 1.0 / 3.0) * (pow(1.0 + a, 1.0 / 3.0) + pow(1.0 - a, 1.0 / 3.0));
+    let z2 = sqrt(3.0 * a * a + z1 * z1);
+    return m * (3.0 + z2 - sqrt((3.0 - z1) * (3.0 + z1 + 2.0 * z2)));
+}
+
+// boyer-lindquist r of a kerr-schild point
+fn kerr_r(p: vec3<f32>, a: f32) -> f32 {
+    let b = 0.5 * (dot(p, p) - a * a);
+    return sqrt(b + sqrt(b * b + a * a * p.y * p.y));
+}
+
+struct KerrSchild {
+    f: f32,
+    // spatial part of the null vector l, the time part is 1
+    l: vec3<f32>,
+}
+
+fn kerr_schild(p: vec3<f32>, m: f32, a: f32) -> KerrSchild {
+    let r = kerr_r(p, a);
+    let r2 = r * r;
+    let a2 = a * a;
+    let f = 2.0 * m * r2 * r / (r2 * r2 + a2 * p.y * p.y);
+    let l = vec3<f32>(
+        (r * p.x - a * p.z) / (r2 + a2),
+        p.y / r,
+        (r * p.z + a * p.x) / (r2 + a2),
+    );
+    return KerrSchild(f, l);
+}
+
+// f * (l^mu p_mu)^2, the only part of the hamiltonian that depends on position
+fn kerr_potential(p: vec3<f32>, momentum: vec3<f32>, m: f32, a: f32) -> f32 {
+    let ks = kerr_schild(p, m, a);
+    let l_dot_p = 1.0 + dot(ks.l, momentum);
+    return ks.f * l_dot_p * l_dot_p;
+}
+
+// d/d(lambda) of (position, momentum)
+fn kerr_photon_derivative(photon: Photon, m: f32, a: f32) -> Photon {
+    let ks = kerr_schild(photon.ro, m, a);
+    let l_dot_p = 1.0 + dot(ks.l, photon.rd);
+    let d_ro = photon.rd - ks.f * l_dot_p * ks.l;
+    // dp/d(lambda) = -dH/dx = 1/2 grad(f * (l.p)^2) with the momentum held fixed
+    // central differences are plenty, the potential is smooth outside the horizon
+    let e = 1e-3 * max(1.0, length(photon.ro));
+    let ex = vec3<f32>(e, 0.0, 0.0);
+    let ey = vec3<f32>(0.0, e, 0.0);
+    let ez = vec3<f32>(0.0, 0.0, e);
+    let grad = vec3<f32>(
+        kerr_potential(photon.ro + ex, photon.rd, m, a) - kerr_potential(photon.ro - ex, photon.rd, m, a),
+        kerr_potential(photon.ro + ey, photon.rd, m, a) - kerr_potential(photon.ro - ey, photon.rd, m, a),
+        kerr_potential(photon.ro + ez, photon.rd, m, a) - kerr_potential(photon.ro - ez, photon.rd, m, a),
+    ) / (2.0 * e);
+    return Photon(d_ro, 0.5 * grad);
+}
+
+// scales the unit direction of a camera ray so that the momentum is null at the camera
+fn kerr_initial_momentum(ro: vec3<f32>, rd: vec3<f32>, m: f32, a: f32) -> vec3<f32> {
+    let ks = kerr_schild(ro, m, a);
+    let l_dot_d = dot(ks.l, rd);
+    // |k rd|^2 - 1 - f (1 + k l.rd)^2 = 0
+    let qa = 1.0 - ks.f * l_dot_d * l_dot_d;
+    let qb = -2.0 * ks.f * l_dot_d;
+    let qc = -(1.0 + ks.f);
+    if abs(qa) < 1e-6 {
+        return rd;
+    }
+    let k = (-qb + sqrt(max(qb * qb - 4.0 * qa * qc, 0.0))) / (2.0 * qa);
+    return k * rd;
+}
+
+// same as get_delta_photon_rk4 but for kerr
+// the affine parameter isn't distance so the step gets scaled
+// so that the photon moves roughly delta_distance
+fn get_delta_photon_kerr_rk4(photon: Photon, delta_distance: f32, m: f32, a: f32) -> Photon {
+    let d1 = kerr_photon_derivative(photon, m, a);
+    let delta_time = delta_distance / max(length(d1.ro), 1e-6);
+
+    let ro_k1 = delta_time * d1.ro;
+    let rd_k1 = delta_time * d1.rd;
+
+    let d2 = kerr_photon_derivative(Photon(photon.ro + 0.5 * ro_k1, photon.rd + 0.5 * rd_k1), m, a);
+    let ro_k2 = delta_time * d2.ro;
+    let rd_k2 = delta_time * d2.rd;
+
+    let d3 = kerr_photon_derivative(Photon(photon.ro + 0.5 * ro_k2, photon.rd + 0.5 * rd_k2), m, a);
+    let ro_k3 = delta_time * d3.ro;
+    let rd_k3 = delta_time * d3.rd;
+
+    let d4 = kerr_photon_derivative(Photon(photon.ro + ro_k3, photon.rd + rd_k3), m, a);
+    let ro_k4 = delta_time * d4.ro;
+    let rd_k4 = delta_time * d4.rd;
+
+    let delta_ro = (ro_k1 + 2.0 * ro_k2 + 2.0 * ro_k3 + ro_k4) / 6.0;
+    let delta_rd = (rd_k1 + 2.0 * rd_k2 + 2.0 * rd_k3 + rd_k4) / 6.0;
+
+    return Photon(delta_ro, delta_rd);
+}
+  
+
+// d/dt of (position, direction) for whichever metric is being used
+// for schwarzschild photon.rd is the velocity, for kerr it's the momentum
+fn photon_derivative(photon: Photon, h2: f32, kerr: bool, m: f32, a: f32) -> Photon {
+    if kerr {
+        return kerr_photon_derivative(photon, m, a);
+    }
+    return Photon(photon.rd, rd_derivative(photon.ro, h2));
+}
+
+fn photon_add_scaled(photon: Photon, k: Photon, s: f32) -> Photon {
+    return Photon(photon.ro + s * k.ro, photon.rd + s * k.rd);
+}
+
+struct AdaptiveStep {
+    delta: Photon,
+    // the derivative at the end of the step, which is the first stage of the next one
+    k_end: Photon,
+    // error estimate relative to the tolerance, the step is good if this is <= 1
+    error_ratio: f32,
+}
+
+// one dormand-prince 5(4) step of size delta_time
+// k1 is the derivative at the start of the step
+fn get_delta_photon_rk45(photon: Photon, k1: Photon, delta_time: f32, tolerance: f32, h2: f32, kerr: bool, m: f32, a: f32) -> AdaptiveStep {
+    let h = delta_time;
+
+    var y = photon_add_scaled(photon, k1, h * (1.0 / 5.0));
+    let k2 = photon_derivative(y, h2, kerr, m, a);
+
+    y = photon_add_scaled(photon, k1, h * (3.0 / 40.0));
+    y = photon_add_scaled(y, k2, h * (9.0 / 40.0));
+    let k3 = photon_derivative(y, h2, kerr, m, a);
+
+    y = photon_add_scaled(photon, k1, h * (44.0 / 45.0));
+    y = photon_add_scaled(y, k2, h * (-56.0 / 15.0));
+    y = photon_add_scaled(y, k3, h * (32.0 / 9.0));
+    let k4 = photon_derivative(y, h2, kerr, m, a);
+
+    y = photon_add_scaled(photon, k1, h * (19372.0 / 6561.0));
+    y = photon_add_scaled(y, k2, h * (-25360.0 / 2187.0));
+    y = photon_add_scaled(y, k3, h * (64448.0 / 6561.0));
+    y = photon_add_scaled(y, k4, h * (-212.0 / 729.0));
+    let k5 = photon_derivative(y, h2, kerr, m, a);
+
+    y = photon_add_scaled(photon, k1, h * (9017.0 / 3168.0));
+    y = photon_add_scaled(y, k2, h * (-355.0 / 33.0));
+    y = photon_add_scaled(y, k3, h * (46732.0 / 5247.0));
+    y = photon_add_scaled(y, k4, h * (49.0 / 176.0));
+    y = photon_add_scaled(y, k5, h * (-5103.0 / 18656.0));
+    let k6 = photon_derivative(y, h2, kerr, m, a);
+
+    // 5th order solution
+    let delta_ro = h * ((35.0 / 384.0) * k1.ro + (500.0 / 1113.0) * k3.ro + (125.0 / 192.0) * k4.ro + (-2187.0 / 6784.0) * k5.ro + (11.0 / 84.0) * k6.ro);
+    let delta_rd = h * ((35.0 / 384.0) * k1.rd + (500.0 / 1113.0) * k3.rd + (125.0 / 192.0) * k4.rd + (-2187.0 / 6784.0) * k5.rd + (11.0 / 84.0) * k6.rd);
+    let k7 = photon_derivative(Photon(photon.ro + delta_ro, photon.rd + delta_rd), h2, kerr, m, a);
+
+    // difference between the 5th and the embedded 4th order solutions
+    let error_ro = h * ((71.0 / 57600.0) * k1.ro + (-71.0 / 16695.0) * k3.ro + (71.0 / 1920.0) * k4.ro + (-17253.0 / 339200.0) * k5.ro + (22.0 / 525.0) * k6.ro + (-1.0 / 40.0) * k7.ro);
+    let error_rd = h * ((71.0 / 57600.0) * k1.rd + (-71.0 / 16695.0) * k3.rd + (71.0 / 1920.0) * k4.rd + (-17253.0 / 339200.0) * k5.rd + (22.0 / 525.0) * k6.rd + (-1.0 / 40.0) * k7.rd);
+
+    let error_ratio = max(
+        length(error_ro) / (tolerance * max(1.0, length(photon.ro))),
+        length(error_rd) / (tolerance * max(1.0, length(photon.rd))),
+    );
+
+    return AdaptiveStep(Photon(delta_ro, delta_rd), k7, error_ratio);
+}
+
+// schwarzschild is spherically symmetric, so a photon stays in the plane of ro and rd
+// in that plane u = 1 / r as a function of the angle phi around the black hole follows the binet equation
+// u'' + u = 3 M u^2
+// which is the same force as rd_derivative, but it's one dimensional and h is exactly conserved
+struct Orbit {
+    // the plane, e1 is where the photon started from and e2 is the way it goes round
+    e1: vec3<f32>,
+    e2: vec3<f32>,
+    // |ro x rd|
+    h: f32,
+    // 1 / r
+    u: f32,
+    // du / dphi
+    w: f32,
+    phi: f32,
+}
+
+// rd has to be a unit vector, and not straight at or away from the black hole
+fn orbit_from_photon(photon: Photon, h2: f32) -> Orbit {
+    let r = length(photon.ro);
+    let e1 = photon.ro / r;
+    let h = sqrt(h2);
+    // dr/dt = -h u', see orbit_photon
+    return Orbit(e1, normalize(photon.rd - dot(photon.rd, e1) * e1), h, 1.0 / r, -dot(photon.rd, e1) / h, 0.0);
+}
+
+fn orbit_acceleration(u: f32, m: f32) -> f32 {
+    return 3.0 * m * u * u - u;
+}
+
+// rk4 in phi
+fn step_orbit(orbit: Orbit, delta_phi: f32, m: f32) -> Orbit {
+    let u_k1 = orbit.w;
+    let w_k1 = orbit_acceleration(orbit.u, m);
+    let u_k2 = orbit.w + 0.5 * delta_phi * w_k1;
+    let w_k2 = orbit_acceleration(orbit.u + 0.5 * delta_phi * u_k1, m);
+    let u_k3 = orbit.w + 0.5 * delta_phi * w_k2;
+    let w_k3 = orbit_acceleration(orbit.u + 0.5 * delta_phi * u_k2, m);
+    let u_k4 = orbit.w + delta_phi * w_k3;
+    let w_k4 = orbit_acceleration(orbit.u + delta_phi * u_k3, m);
+
+    var next = orbit;
+    next.u += delta_phi * (u_k1 + 2.0 * u_k2 + 2.0 * u_k3 + u_k4) / 6.0;
+    next.w += delta_phi * (w_k1 + 2.0 * w_k2 + 2.0 * w_k3 + w_k4) / 6.0;
+    next.phi += delta_phi;
+    return next;
+}
+
+// the change in phi that moves the photon about delta_distance
+fn orbit_delta_phi(orbit: Orbit, delta_distance: f32) -> f32 {
+    return delta_distance * orbit.u * orbit.u / length(vec2<f32>(orbit.u, orbit.w));
+}
+
+// back in 3d, with rd scaled like the other integrators have it so ro x rd stays h
+fn orbit_photon(orbit: Orbit) -> Photon {
+    let radial = cos(orbit.phi) * orbit.e1 + sin(orbit.phi) * orbit.e2;
+    let tangential = cos(orbit.phi) * orbit.e2 - sin(orbit.phi) * orbit.e1;
+    // past u = 0 it's at infinity, only the direction is any use then
+    let orbit_u = max(orbit.u, 0.0);
+    return Photon(radial / max(orbit_u, 1e-6), orbit.h * (orbit_u * tangential - orbit.w * radial));
+}
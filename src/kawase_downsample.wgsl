@@ -1,12 +1,4 @@
-struct VertexOutput {
-    // Mark output position as invariant so it's safe to use it with depth test Equal.
-    // Without @invariant, different usages in different render pipelines might optimize differently,
-    // causing slightly different results.
-    @invariant @builtin(position)
-    position: vec4f,
-    @location(0)
-    texcoord: vec2f,
-};
+#include "src/fullscreen.wgsl"
 
 @group(0) @binding(0)
 var input_texture: texture_2d<f32>;
@@ -14,7 +6,7 @@ var input_texture: texture_2d<f32>;
 var texture_sampler: sampler;
 
 @group(0) @binding(2)
-var<uniform> resolution: vec2u;
+var<uniform> offset: f32;
 
 // https://www.shadertoy.com/view/3td3W8
 @fragment
@@ -23,8 +15,7 @@ fn main( in: VertexOutput ) -> @location(0) vec4f
     // vec2 uv = vec2(fragCoord.xy / (iResolution.xy / 2.0));
     let uv = in.texcoord;
     // vec2 halfpixel = 0.5 / (iResolution.xy / 2.0);
-    let halfpixel = 0.5 / (vec2f(resolution));
-    let offset = 3.0;
+    let halfpixel = 0.5 / vec2f(textureDimensions(input_texture));
 
     var sum = textureSample(input_texture, texture_sampler, uv) * 4.0;
     sum += textureSample(input_texture, texture_sampler, uv - halfpixel.xy * offset);
@@ -32,6 +23,5 @@ fn main( in: VertexOutput ) -> @location(0) vec4f
     sum += textureSample(input_texture, texture_sampler, uv + vec2f(halfpixel.x, -halfpixel.y) * offset);
     sum += textureSample(input_texture, texture_sampler, uv - vec2f(halfpixel.x, -halfpixel.y) * offset);
 
-    // return sum / 8.0;
-    return textureSample(input_texture, texture_sampler, uv);
+    return sum / 8.0;
 }
\ No newline at end of file
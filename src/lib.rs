@@ -19,15 +19,32 @@ use winit::{
 #[macro_use]
 mod smart_include;
 
-mod bloom;
-mod camera;
+pub mod bloom;
+pub mod camera;
+pub mod camera_path;
+pub mod cpu_tracer;
+pub mod deflection_lut;
+pub mod dynamic_resolution;
+pub mod fullscreen_pass;
+pub mod gpu_profiler;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+mod hdr;
 // mod downsampling;
 mod indices;
 mod otheruniforms;
+mod parameter_panel;
 mod podbool;
-mod scene;
+pub mod post_processing;
+pub mod progressive;
+pub mod render_graph;
+pub mod scene;
+pub mod screenshot;
+pub mod shader;
 mod settings;
+pub mod taa;
 mod texture;
+pub mod tone_mapping;
 mod time_replacement;
 mod uniforms;
 mod uniformscontroller;
@@ -35,13 +52,9 @@ mod uniformscontroller;
 mod vertex;
 mod vertices;
 // mod gaussian_blur;
-mod kawase_downsampling;
-mod kawase_upsampling;
 // mod kawase_mixing_upsampling;
 
 mod blur;
-mod copy;
-mod remix;
 mod state;
 use state::State;
 
@@ -1,6 +1,9 @@
-use std::{fmt::Debug, num::NonZeroU64};
+use std::{any::Any, fmt::Debug, num::NonZeroU64};
 
-use crate::uniformscontroller::{Increment, Opposite};
+use crate::{
+    podbool::PodBool,
+    uniformscontroller::{Increment, Opposite},
+};
 use encase::{
     internal::{WriteInto, Writer},
     ShaderType,
@@ -55,12 +58,17 @@ pub trait IncValueTrait: Debug {
     // could change this into a more generic UniformBuffer thing like encase does
     fn write_into_buffer(&self, buffer: &mut Vec<u8>, offset: usize);
     fn size(&self) -> NonZeroU64;
+    // the value without knowing its type, for setting it from outside the shader controls
+    fn value(&self) -> &dyn Any;
+    fn value_mut(&mut self) -> &mut dyn Any;
+    // what increment and decrement change it by, also without knowing its type
+    fn inc(&self) -> &dyn Any;
 }
 
 impl<T, I> IncValueTrait for IncValue<T, I>
 where
-    T: Increment<I> + Debug,
-    I: Opposite<I> + Debug,
+    T: Increment<I> + Debug + 'static,
+    I: Opposite<I> + Debug + 'static,
     T: ShaderType + WriteInto,
 {
     fn increment(&mut self) {
@@ -78,6 +86,58 @@ where
     fn size(&self) -> NonZeroU64 {
         self.value.size()
     }
+    fn value(&self) -> &dyn Any {
+        &self.value
+    }
+    fn value_mut(&mut self) -> &mut dyn Any {
+        &mut self.value
+    }
+    fn inc(&self) -> &dyn Any {
+        &self.inc
+    }
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.to_ascii_lowercase().as_str() {
+        "true" | "on" | "1" => Some(true),
+        "false" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+impl OtherUniform {
+    pub fn value_to_string(&self) -> String {
+        let value = self.inc_value.value();
+        if let Some(value) = value.downcast_ref::<f32>() {
+            value.to_string()
+        } else if let Some(value) = value.downcast_ref::<u32>() {
+            value.to_string()
+        } else if let Some(value) = value.downcast_ref::<i32>() {
+            value.to_string()
+        } else if let Some(value) = value.downcast_ref::<PodBool>() {
+            value.to_string()
+        } else {
+            format!("{:?}", self.inc_value)
+        }
+    }
+
+    pub fn set_from_str(&mut self, s: &str) -> Result<(), String> {
+        let s = s.trim();
+        let error = || format!("\"{}\" isn't a valid value for {}", s, self.label);
+        let value = self.inc_value.value_mut();
+        if let Some(value) = value.downcast_mut::<f32>() {
+            *value = s.parse().map_err(|_| error())?;
+        } else if let Some(value) = value.downcast_mut::<u32>() {
+            *value = s.parse().map_err(|_| error())?;
+        } else if let Some(value) = value.downcast_mut::<i32>() {
+            *value = s.parse().map_err(|_| error())?;
+        } else if let Some(value) = value.downcast_mut::<PodBool>() {
+            value.set(parse_bool(s).ok_or_else(error)?);
+        } else {
+            return Err(format!("{} can't be set from text", self.label));
+        }
+        Ok(())
+    }
 }
 
 // mental gymnastics ends
@@ -87,6 +147,8 @@ pub struct OtherUniforms<const N: usize> {
     pub negative_modifier_key_code: KeyCode,
     pub other_uniforms: [OtherUniform; N],
     pub modifier_number_pressed: Option<usize>,
+    // holding shift with a number selects 10 to 19
+    pub shift_pressed: bool,
 }
 
 impl<const N: usize> OtherUniforms<N> {
@@ -100,7 +162,29 @@ impl<const N: usize> OtherUniforms<N> {
             negative_modifier_key_code,
             other_uniforms,
             modifier_number_pressed: None,
+            shift_pressed: false,
+        }
+    }
+    // by number (the same one as the key that selects it) or by label
+    pub fn find(&self, name: &str) -> Option<&OtherUniform> {
+        if let Ok(index) = name.trim().parse::<usize>() {
+            return self.other_uniforms.get(index);
         }
+        self.other_uniforms
+            .iter()
+            .find(|other_uniform| other_uniform.label.eq_ignore_ascii_case(name.trim()))
+    }
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut OtherUniform> {
+        if let Ok(index) = name.trim().parse::<usize>() {
+            return self.other_uniforms.get_mut(index);
+        }
+        self.other_uniforms
+            .iter_mut()
+            .find(|other_uniform| other_uniform.label.eq_ignore_ascii_case(name.trim()))
+    }
+    // none if there's no such uniform or it isn't a T
+    pub fn value<T: Copy + 'static>(&self, name: &str) -> Option<T> {
+        self.find(name)?.inc_value.value().downcast_ref::<T>().copied()
     }
     pub fn uniform_buffer_content(&self) -> Vec<u8> {
         let mut buffer: Vec<u8> = Vec::new();
@@ -118,6 +202,10 @@ impl<const N: usize> OtherUniforms<N> {
     }
     pub fn process_event(&mut self, event: &WindowEvent) -> bool {
         match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.shift_pressed = modifiers.state().shift_key();
+                false
+            }
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -135,6 +223,7 @@ impl<const N: usize> OtherUniforms<N> {
                     return false;
                 }
                 if let Some(number) = number_from_virtual_key_code(code) {
+                    let number = if self.shift_pressed { number + 10 } else { number };
                     self.modifier_number_pressed = Some(number);
                     println!(
                         "{}",
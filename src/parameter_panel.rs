@@ -0,0 +1,434 @@
+// an egui window with every parameter in it, drawn over the surface after tone mapping
+// f1 shows and hides it, while it's showing it gets the window's events before anything else
+// update builds it and applies whatever was changed, then render draws it
+
+use std::any::Any;
+
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+    window::Window,
+};
+
+use crate::{
+    bloom::{BloomSettings, DEFAULT_LEVEL_WEIGHT},
+    gpu_profiler::GpuProfiler,
+    otheruniforms::OtherUniform,
+    podbool::PodBool,
+    post_processing::PostProcessing,
+    scene::Scene,
+    screenshot::ScreenshotController,
+    settings::Settings,
+    tone_mapping::ToneMappingCurve,
+};
+
+// what update left for render to draw
+struct Frame {
+    primitives: Vec<egui::ClippedPrimitive>,
+    pixels_per_point: f32,
+    // the textures egui is done with once this is drawn
+    free: Vec<egui::TextureId>,
+}
+
+pub struct ParameterPanel {
+    context: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    visible: bool,
+    frame: Option<Frame>,
+}
+
+impl ParameterPanel {
+    // output_format is the surface's, it's drawn straight onto it
+    pub fn new(device: &wgpu::Device, window: &Window, output_format: wgpu::TextureFormat) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            None,
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        let renderer = egui_wgpu::Renderer::new(device, output_format, None, 1, false);
+        Self {
+            context,
+            state,
+            renderer,
+            // it starts open so the controls can be found
+            visible: true,
+            frame: None,
+        }
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        println!(
+            "parameter panel: {}",
+            if visible { "shown" } else { "hidden" }
+        );
+    }
+
+    // returns whether the panel used the event, then the other controls shouldn't see it
+    pub fn process_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::F1),
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.set_visible(!self.visible);
+            return true;
+        }
+        if !self.visible {
+            return false;
+        }
+        self.state.on_window_event(window, event).consumed
+    }
+
+    // has to be called once a frame before anything is rendered, so the changes are in this frame
+    #[allow(clippy::too_many_arguments)]
+    pub fn update(
+        &mut self,
+        window: &Window,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &mut Scene,
+        post_processing: &mut PostProcessing,
+        settings: &mut Settings,
+        screenshot_controller: &mut ScreenshotController,
+    ) {
+        // the last frame wasn't drawn, like when the surface wasn't available
+        if let Some(frame) = self.frame.take() {
+            self.free_textures(&frame);
+        }
+        if !self.visible {
+            return;
+        }
+        let raw_input = self.state.take_egui_input(window);
+        let output = self.context.run(raw_input, |context| {
+            egui::Window::new("parameters")
+                .default_width(340.0)
+                .vscroll(true)
+                .show(context, |ui| {
+                    egui::CollapsingHeader::new("scene")
+                        .default_open(true)
+                        .show(ui, |ui| scene_ui(ui, queue, scene));
+                    egui::CollapsingHeader::new("camera")
+                        .default_open(true)
+                        .show(ui, |ui| camera_ui(ui, scene));
+                    egui::CollapsingHeader::new("post processing")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            post_processing_ui(ui, device, queue, post_processing)
+                        });
+                    egui::CollapsingHeader::new("settings")
+                        .default_open(true)
+                        .show(ui, |ui| settings_ui(ui, settings));
+                    egui::CollapsingHeader::new("screenshot")
+                        .default_open(true)
+                        .show(ui, |ui| screenshot_ui(ui, device, screenshot_controller));
+                });
+        });
+        self.state
+            .handle_platform_output(window, output.platform_output);
+        let primitives = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        // here rather than in render so the font atlas can't be missed if a frame isn't drawn
+        for (id, image_delta) in &output.textures_delta.set {
+            self.renderer
+                .update_texture(device, queue, *id, image_delta);
+        }
+        self.frame = Some(Frame {
+            primitives,
+            pixels_per_point: output.pixels_per_point,
+            free: output.textures_delta.free,
+        });
+    }
+
+    fn free_textures(&mut self, frame: &Frame) {
+        for id in &frame.free {
+            self.renderer.free_texture(id);
+        }
+    }
+
+    // draws what update built on top of output_view, has to go after post processing
+    // it's timed as "parameter panel" if there's a profiler
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        size: [u32; 2],
+        profiler: Option<&mut GpuProfiler>,
+    ) {
+        let Some(frame) = self.frame.take() else {
+            return;
+        };
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: size,
+            pixels_per_point: frame.pixels_per_point,
+        };
+        let command_buffers = self.renderer.update_buffers(
+            device,
+            queue,
+            encoder,
+            &frame.primitives,
+            &screen_descriptor,
+        );
+        queue.submit(command_buffers);
+        {
+            let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("parameter panel render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: output_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // it goes over the image
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: profiler
+                    .and_then(|profiler| profiler.render_pass("parameter panel")),
+                occlusion_query_set: None,
+            });
+            self.renderer.render(
+                &mut render_pass.forget_lifetime(),
+                &frame.primitives,
+                &screen_descriptor,
+            );
+        }
+        self.free_textures(&frame);
+    }
+}
+
+// how far dragging a value one pixel moves it, a tenth of what page up and down do
+fn drag_speed(inc: &dyn Any) -> f64 {
+    let inc = if let Some(inc) = inc.downcast_ref::<f32>() {
+        *inc as f64
+    } else if let Some(inc) = inc.downcast_ref::<i32>() {
+        *inc as f64
+    } else if let Some(inc) = inc.downcast_ref::<u32>() {
+        *inc as f64
+    } else {
+        1.0
+    };
+    0.1 * inc.abs()
+}
+
+// a checkbox for a PodBool, something to drag or type into for the numbers
+// returns whether it changed
+fn other_uniform_ui(ui: &mut egui::Ui, other_uniform: &mut OtherUniform) -> bool {
+    let speed = drag_speed(other_uniform.inc_value.inc());
+    let value = other_uniform.inc_value.value_mut();
+    if let Some(value) = value.downcast_mut::<PodBool>() {
+        let mut checked = value.get();
+        let changed = ui.checkbox(&mut checked, "").changed();
+        value.set(checked);
+        changed
+    } else if let Some(value) = value.downcast_mut::<f32>() {
+        ui.add(egui::DragValue::new(value).speed(speed)).changed()
+    } else if let Some(value) = value.downcast_mut::<u32>() {
+        ui.add(egui::DragValue::new(value).speed(speed)).changed()
+    } else if let Some(value) = value.downcast_mut::<i32>() {
+        ui.add(egui::DragValue::new(value).speed(speed)).changed()
+    } else {
+        ui.label(other_uniform.value_to_string());
+        false
+    }
+}
+
+// every OtherUniform, with the number key that selects it
+fn scene_ui(ui: &mut egui::Ui, queue: &wgpu::Queue, scene: &mut Scene) {
+    let mut changed = false;
+    egui::Grid::new("other uniforms")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            for (number, other_uniform) in
+                scene.other_uniforms.other_uniforms.iter_mut().enumerate()
+            {
+                let key = if number < 10 {
+                    format!("{}", number)
+                } else {
+                    format!("shift + {}", number - 10)
+                };
+                ui.label(&other_uniform.label).on_hover_text(format!(
+                    "{} then page up and page down on the keyboard",
+                    key
+                ));
+                changed |= other_uniform_ui(ui, other_uniform);
+                ui.end_row();
+            }
+        });
+    if changed {
+        scene.write_other_uniforms(queue);
+    }
+}
+
+fn camera_ui(ui: &mut egui::Ui, scene: &mut Scene) {
+    let camera = &mut scene.camera;
+    let mut changed = false;
+    egui::Grid::new("camera")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("position");
+            ui.horizontal(|ui| {
+                for axis in 0..3 {
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut camera.pos[axis]).speed(0.1))
+                        .changed();
+                }
+            });
+            ui.end_row();
+
+            ui.label("direction");
+            ui.label(format!(
+                "{:.3}, {:.3}, {:.3}",
+                camera.dir.x, camera.dir.y, camera.dir.z
+            ));
+            ui.end_row();
+
+            ui.label("vertical fov");
+            let mut fov = camera.fovy.to_degrees();
+            if ui
+                .add(egui::Slider::new(&mut fov, 10.0..=170.0).suffix("°"))
+                .changed()
+            {
+                camera.fovy = fov.to_radians();
+                changed = true;
+            }
+            ui.end_row();
+
+            ui.label("speed").on_hover_text("q and e on the keyboard");
+            ui.add(
+                egui::DragValue::new(&mut scene.camera_controller.speed)
+                    .speed(0.1)
+                    .range(0.0..=f32::MAX),
+            );
+            ui.end_row();
+        });
+    if changed {
+        scene.mark_changed();
+    }
+}
+
+fn post_processing_ui(
+    ui: &mut egui::Ui,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    post_processing: &mut PostProcessing,
+) {
+    let mut progressive = post_processing.progressive.enabled();
+    if ui
+        .checkbox(&mut progressive, "progressive accumulation (r)")
+        .changed()
+    {
+        post_processing.set_progressive(device, progressive);
+    }
+    let mut taa = post_processing.taa.enabled();
+    if ui
+        .checkbox(&mut taa, "temporal anti-aliasing (g)")
+        .changed()
+    {
+        post_processing.set_taa(device, taa);
+    }
+    let mut dynamic_resolution = post_processing.dynamic_resolution.enabled();
+    if ui
+        .checkbox(&mut dynamic_resolution, "dynamic resolution (h)")
+        .changed()
+    {
+        post_processing.set_dynamic_resolution(device, dynamic_resolution);
+    }
+
+    ui.separator();
+    let tone_mapping = &mut post_processing.tone_mapping;
+    let mut curve = tone_mapping.curve;
+    egui::ComboBox::from_label("tone mapping (t + 0 to 3)")
+        .selected_text(curve.name())
+        .show_ui(ui, |ui| {
+            for option in ToneMappingCurve::ALL {
+                ui.selectable_value(&mut curve, option, option.name());
+            }
+        });
+    if curve != tone_mapping.curve {
+        tone_mapping.set_curve(curve, queue);
+    }
+    if ui
+        .add(
+            egui::Slider::new(&mut tone_mapping.exposure, -8.0..=8.0)
+                .text("exposure ([ and ])")
+                .suffix(" stops"),
+        )
+        .changed()
+    {
+        tone_mapping.write_uniform(queue);
+    }
+
+    ui.separator();
+    let mut bloom: BloomSettings = post_processing.bloom.settings().clone();
+    ui.add(egui::Slider::new(&mut bloom.levels, 1..=9).text("bloom levels (b + 1 to 9)"));
+    ui.add(egui::Slider::new(&mut bloom.threshold, 0.0..=4.0).text("bloom threshold (, and .)"));
+    ui.add(egui::Slider::new(&mut bloom.intensity, 0.0..=2.0).text("bloom intensity (- and =)"));
+    ui.add(egui::Slider::new(&mut bloom.radius, 0.0..=10.0).text("bloom radius (; and ')"));
+    // the levels without a weight of their own get one when theirs is changed
+    for level in 0..bloom.levels {
+        let mut weight = bloom.level_weight(level);
+        if ui
+            .add(egui::Slider::new(&mut weight, 0.0..=1.0).text(format!("level {} weight", level)))
+            .changed()
+        {
+            if bloom.level_weights.len() <= level {
+                bloom.level_weights.resize(level + 1, DEFAULT_LEVEL_WEIGHT);
+            }
+            bloom.level_weights[level] = weight;
+        }
+    }
+    if &bloom != post_processing.bloom.settings() {
+        post_processing.set_bloom_settings(device, queue, bloom);
+    }
+}
+
+fn settings_ui(ui: &mut egui::Ui, settings: &mut Settings) {
+    let mut limited = settings.max_frame_rate.is_some();
+    let mut max_frame_rate = settings.max_frame_rate.unwrap_or(80.0);
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut limited, "max frame rate (f + 1 to 9, 0 for none)");
+        ui.add_enabled(
+            limited,
+            egui::Slider::new(&mut max_frame_rate, 1.0..=240.0).suffix(" fps"),
+        );
+    });
+    settings.max_frame_rate = limited.then_some(max_frame_rate);
+}
+
+fn screenshot_ui(
+    ui: &mut egui::Ui,
+    device: &wgpu::Device,
+    screenshot_controller: &mut ScreenshotController,
+) {
+    let max_size = device.limits().max_texture_dimension_2d;
+    let size = &mut screenshot_controller.size;
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut size.x).range(1..=max_size));
+        ui.label("x");
+        ui.add(egui::DragValue::new(&mut size.y).range(1..=max_size));
+    });
+    ui.horizontal(|ui| {
+        if ui.button("window size (f12)").clicked() {
+            screenshot_controller.request(None);
+        }
+        if ui.button("this size (shift + f12)").clicked() {
+            screenshot_controller.request(Some(screenshot_controller.size));
+        }
+    });
+}
@@ -19,10 +19,10 @@ impl PartialEq for PodBool {
 
 impl PodBool {
     pub fn r#true() -> Self {
-        Self { inner: 0 }
+        Self { inner: 1 }
     }
     pub fn r#false() -> Self {
-        Self { inner: 1 }
+        Self { inner: 0 }
     }
     pub fn set(&mut self, value: bool) {
         if value {
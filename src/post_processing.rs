@@ -0,0 +1,329 @@
+// everything between the scene and the surface, declared as a render graph
+// the scene renders into scene_view and scene_blackout_view, then render runs the rest
+// a new effect goes in declare, between the textures it reads and the pass that reads it
+
+use std::time::Duration;
+
+use glam::{UVec2, Vec2};
+use winit::event::WindowEvent;
+
+use crate::{
+    bloom::{Bloom, BloomSettings},
+    camera::Camera,
+    dynamic_resolution::DynamicResolution,
+    gpu_profiler::GpuProfiler,
+    progressive::Progressive,
+    render_graph::{RenderGraph, TextureHandle, TextureSize},
+    taa::Taa,
+    tone_mapping::ToneMapping,
+};
+
+pub struct PostProcessing {
+    pub graph: RenderGraph,
+    // the scene renders at its render scale
+    pub dynamic_resolution: DynamicResolution,
+    pub progressive: Progressive,
+    // only in the graph while progressive is off, the accumulation replaces it
+    pub taa: Taa,
+    pub bloom: Bloom,
+    pub tone_mapping: ToneMapping,
+    // whether the graph has the upscaling, accumulation or taa passes in it
+    dynamic_resolution_declared: bool,
+    progressive_declared: bool,
+    taa_declared: bool,
+
+    // the scene's two targets, graph inputs since nothing in the graph writes them
+    scene_texture: TextureHandle,
+    scene_blackout_texture: TextureHandle,
+    // the image with its bloom, what tone mapping reads
+    tone_mapping_input: TextureHandle,
+
+    // what everything before tone mapping renders to
+    hdr_format: wgpu::TextureFormat,
+    // what the scene needs its textures usable as, see SceneBackend::output_usage
+    scene_usage: wgpu::TextureUsages,
+    // what tone mapping renders to
+    output_format: wgpu::TextureFormat,
+}
+
+impl PostProcessing {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        hdr_format: wgpu::TextureFormat,
+        scene_usage: wgpu::TextureUsages,
+        bloom_settings: BloomSettings,
+    ) -> Self {
+        let dynamic_resolution = DynamicResolution::default();
+        let progressive = Progressive::new(device);
+        let taa = Taa::new(device);
+        let mut bloom = Bloom::new(device, bloom_settings);
+        let tone_mapping = ToneMapping::new(device);
+        let mut graph = RenderGraph::new(config.width, config.height);
+        let (scene_texture, scene_blackout_texture, tone_mapping_input) = Self::declare(
+            device,
+            &mut graph,
+            hdr_format,
+            scene_usage,
+            config.format,
+            &dynamic_resolution,
+            &progressive,
+            &taa,
+            &mut bloom,
+            &tone_mapping,
+        );
+        Self {
+            graph,
+            dynamic_resolution_declared: dynamic_resolution.enabled(),
+            dynamic_resolution,
+            progressive_declared: progressive.enabled(),
+            taa_declared: Self::taa_active(&progressive, &taa),
+            progressive,
+            taa,
+            bloom,
+            tone_mapping,
+
+            scene_texture,
+            scene_blackout_texture,
+            tone_mapping_input,
+
+            hdr_format,
+            scene_usage,
+            output_format: config.format,
+        }
+    }
+
+    fn taa_active(progressive: &Progressive, taa: &Taa) -> bool {
+        taa.enabled() && !progressive.enabled()
+    }
+
+    // declares everything into an empty graph and builds it
+    // returns the scene's two textures
+    #[allow(clippy::too_many_arguments)]
+    fn declare(
+        device: &wgpu::Device,
+        graph: &mut RenderGraph,
+        hdr_format: wgpu::TextureFormat,
+        scene_usage: wgpu::TextureUsages,
+        output_format: wgpu::TextureFormat,
+        dynamic_resolution: &DynamicResolution,
+        progressive: &Progressive,
+        taa: &Taa,
+        bloom: &mut Bloom,
+        tone_mapping: &ToneMapping,
+    ) -> (TextureHandle, TextureHandle, TextureHandle) {
+        let scene_size = if dynamic_resolution.enabled() {
+            TextureSize::Scaled
+        } else {
+            TextureSize::Full
+        };
+        let scene_texture = graph.add_texture("scene texture", scene_size, hdr_format);
+        let scene_blackout_texture =
+            graph.add_texture("scene blackout texture", scene_size, hdr_format);
+        graph.add_usage(scene_texture, scene_usage);
+        graph.add_usage(scene_blackout_texture, scene_usage);
+
+        let (image, blackout) = if dynamic_resolution.enabled() {
+            (
+                dynamic_resolution.add_to_graph(graph, device, "scene", scene_texture),
+                dynamic_resolution.add_to_graph(
+                    graph,
+                    device,
+                    "scene blackout",
+                    scene_blackout_texture,
+                ),
+            )
+        } else {
+            (scene_texture, scene_blackout_texture)
+        };
+        let (image, blackout) = if progressive.enabled() {
+            (
+                progressive.add_to_graph(graph, device, "scene", image),
+                progressive.add_to_graph(graph, device, "scene blackout", blackout),
+            )
+        } else if Self::taa_active(progressive, taa) {
+            (
+                taa.add_to_graph(graph, device, "scene", image),
+                taa.add_to_graph(graph, device, "scene blackout", blackout),
+            )
+        } else {
+            (image, blackout)
+        };
+        let bloomed = bloom.add_to_graph(graph, device, image, blackout);
+        // so the headless renderer can read it back for exr files
+        graph.add_usage(bloomed, wgpu::TextureUsages::COPY_SRC);
+        tone_mapping.add_to_graph(graph, device, bloomed, output_format);
+
+        graph
+            .build(device)
+            .expect("the post processing graph is declared correctly");
+        (scene_texture, scene_blackout_texture, bloomed)
+    }
+
+    // for changes to the declarations, resizing doesn't need this
+    pub fn rebuild(&mut self, device: &wgpu::Device) {
+        let size = self.graph.output_size();
+        self.graph = RenderGraph::new(size.x, size.y);
+        (
+            self.scene_texture,
+            self.scene_blackout_texture,
+            self.tone_mapping_input,
+        ) = Self::declare(
+            device,
+            &mut self.graph,
+            self.hdr_format,
+            self.scene_usage,
+            self.output_format,
+            &self.dynamic_resolution,
+            &self.progressive,
+            &self.taa,
+            &mut self.bloom,
+            &self.tone_mapping,
+        );
+        self.graph
+            .set_render_scale(device, self.dynamic_resolution.render_scale());
+        self.dynamic_resolution_declared = self.dynamic_resolution.enabled();
+        self.progressive_declared = self.progressive.enabled();
+        self.taa_declared = Self::taa_active(&self.progressive, &self.taa);
+        // the history textures are new
+        self.progressive.reset();
+        self.taa.reset();
+    }
+
+    fn needs_rebuild(&self) -> bool {
+        self.bloom.needs_rebuild()
+            || self.dynamic_resolution.enabled() != self.dynamic_resolution_declared
+            || self.progressive.enabled() != self.progressive_declared
+            || Self::taa_active(&self.progressive, &self.taa) != self.taa_declared
+    }
+
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        self.graph.view(self.scene_texture)
+    }
+
+    pub fn scene_blackout_view(&self) -> &wgpu::TextureView {
+        self.graph.view(self.scene_blackout_texture)
+    }
+
+    // the linear image before exposure and tone mapping, in hdr_format
+    pub fn tone_mapping_input(&self) -> &wgpu::Texture {
+        self.graph.texture(self.tone_mapping_input)
+    }
+
+    pub fn hdr_format(&self) -> wgpu::TextureFormat {
+        self.hdr_format
+    }
+
+    // the resolution the scene renders at
+    pub fn scene_size(&self) -> UVec2 {
+        self.graph.size(self.scene_texture)
+    }
+
+    pub fn set_bloom_settings(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        settings: BloomSettings,
+    ) {
+        self.bloom.set_settings(queue, settings);
+        if self.needs_rebuild() {
+            self.rebuild(device);
+        }
+    }
+
+    pub fn set_progressive(&mut self, device: &wgpu::Device, enabled: bool) {
+        self.progressive.set_enabled(enabled);
+        if self.needs_rebuild() {
+            self.rebuild(device);
+        }
+    }
+
+    pub fn set_taa(&mut self, device: &wgpu::Device, enabled: bool) {
+        self.taa.set_enabled(enabled);
+        if self.needs_rebuild() {
+            self.rebuild(device);
+        }
+    }
+
+    pub fn set_dynamic_resolution(&mut self, device: &wgpu::Device, enabled: bool) {
+        self.dynamic_resolution.set_enabled(enabled);
+        if self.needs_rebuild() {
+            self.rebuild(device);
+        }
+    }
+
+    // has to be called once a frame with how long it took to render
+    // returns whether the scene size changed, the scene has to be resized to scene_size then
+    pub fn update_render_scale(
+        &mut self,
+        device: &wgpu::Device,
+        frame_time: Duration,
+        target_frame_time: Duration,
+    ) -> bool {
+        let Some(render_scale) = self
+            .dynamic_resolution
+            .update(frame_time, target_frame_time)
+        else {
+            return false;
+        };
+        let old_size = self.scene_size();
+        self.graph.set_render_scale(device, render_scale);
+        println!("render scale: {}", render_scale);
+        self.scene_size() != old_size
+    }
+
+    // resizing reallocates the history, so the average starts over
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.graph.resize(device, width, height);
+        self.progressive.reset();
+        self.taa.reset();
+    }
+
+    // has to be called once a frame before the scene renders, with the camera it renders with
+    // changed is whether anything the scene renders changed since the last frame
+    // returns where in their pixel this frame's rays should go through, in pixels
+    pub fn next_frame(&mut self, queue: &wgpu::Queue, camera: &Camera, changed: bool) -> Vec2 {
+        if self.progressive.enabled() {
+            self.progressive.next_frame(queue, changed)
+        } else if self.taa.enabled() {
+            self.taa.next_frame(queue, camera)
+        } else {
+            Vec2::ZERO
+        }
+    }
+
+    pub fn process_event(
+        &mut self,
+        event: &WindowEvent,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> bool {
+        let dynamic_resolution_handled = self.dynamic_resolution.process_event(event);
+        let progressive_handled = self.progressive.process_event(event);
+        let taa_handled = self.taa.process_event(event);
+        let tone_mapping_handled = self.tone_mapping.process_event(event, queue);
+        let bloom_handled = self.bloom.process_event(event, queue);
+        if self.needs_rebuild() {
+            self.rebuild(device);
+        }
+        dynamic_resolution_handled
+            || progressive_handled
+            || taa_handled
+            || tone_mapping_handled
+            || bloom_handled
+    }
+
+    // see shader.rs
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.graph.reload_shaders(device, changed);
+    }
+
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        profiler: Option<&mut GpuProfiler>,
+    ) {
+        self.graph.execute(encoder, output_view, profiler);
+    }
+}
@@ -0,0 +1,158 @@
+// progressive accumulation, for converged and anti-aliased stills
+// while it's on, every frame the scene's rays go through a different point inside their
+// pixel and the frame is averaged into the history of the frames before it
+// the average starts over whenever the scene reports a change
+
+use std::sync::Arc;
+
+use glam::{vec2, Vec2};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{
+    fullscreen_pass::FullscreenPass,
+    otheruniforms::BufferContent,
+    render_graph::{RenderGraph, Target, TextureHandle, TextureSize},
+};
+
+// past this the average turns into a moving average
+// Rgba16Float can't hold the difference a smaller weight would make anyway
+pub const MAX_ACCUMULATED_FRAMES: u32 = 64;
+
+// the radical inverse of index in base, a low discrepancy sequence in [0, 1)
+pub(crate) fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0 / base as f32;
+    while index > 0 {
+        result += (index % base) as f32 * fraction;
+        index /= base;
+        fraction /= base as f32;
+    }
+    result
+}
+
+pub struct Progressive {
+    // changing this changes the graph, PostProcessing rebuilds it
+    enabled: bool,
+    // including the frame being rendered
+    accumulated_frames: u32,
+
+    // how much of this frame goes into the average
+    pub weight_uniform_buffer: Arc<wgpu::Buffer>,
+}
+
+impl Progressive {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let weight_uniform_buffer = Arc::new(device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("progressive weight uniform buffer"),
+                contents: &1.0f32.uniform_buffer_content(),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            },
+        ));
+        Self {
+            enabled: false,
+            accumulated_frames: 0,
+
+            weight_uniform_buffer,
+        }
+    }
+
+    // declares the average of input over the frames since the last change
+    // returns the texture holding it
+    pub fn add_to_graph(
+        &self,
+        graph: &mut RenderGraph,
+        device: &wgpu::Device,
+        label: &str,
+        input: TextureHandle,
+    ) -> TextureHandle {
+        let format = graph.format(input);
+        let accumulated = graph.add_texture(
+            &format!("{} accumulated texture", label),
+            TextureSize::Full,
+            format,
+        );
+        let history = graph.add_history(accumulated);
+        let pass_label = format!("{} accumulation", label);
+        graph.add_pass(
+            &pass_label,
+            &[input, history],
+            &[Target::Texture(accumulated)],
+            FullscreenPass::new(
+                device,
+                &pass_label,
+                include_shader!("src/accumulate.wgsl"),
+                2,
+                vec![self.weight_uniform_buffer.clone()],
+                format,
+            ),
+        );
+        accumulated
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.reset();
+        println!(
+            "progressive accumulation: {}",
+            if enabled { "on" } else { "off" }
+        );
+    }
+
+    pub fn accumulated_frames(&self) -> u32 {
+        self.accumulated_frames
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulated_frames = 0;
+    }
+
+    // has to be called once a frame before rendering, changed is whether anything the
+    // scene renders changed since the last frame
+    // returns where in its pixel this frame's rays go through, from -0.5 to 0.5
+    pub fn next_frame(&mut self, queue: &wgpu::Queue, changed: bool) -> Vec2 {
+        if !self.enabled {
+            return Vec2::ZERO;
+        }
+        if changed {
+            self.reset();
+        }
+        self.accumulated_frames += 1;
+        let weight = 1.0 / self.accumulated_frames.min(MAX_ACCUMULATED_FRAMES) as f32;
+        queue.write_buffer(
+            &self.weight_uniform_buffer,
+            0,
+            &weight.uniform_buffer_content(),
+        );
+        let index = self.accumulated_frames;
+        vec2(halton(index, 2), halton(index, 3)) - 0.5
+    }
+
+    // r toggles it, which needs the graph rebuilding
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::KeyR),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                self.set_enabled(!self.enabled);
+                true
+            }
+            _ => false,
+        }
+    }
+}
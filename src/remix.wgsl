@@ -1,12 +1,4 @@
-struct VertexOutput {
-    // Mark output position as invariant so it's safe to use it with depth test Equal.
-    // Without @invariant, different usages in different render pipelines might optimize differently,
-    // causing slightly different results.
-    @invariant @builtin(position)
-    position: vec4f,
-    @location(0)
-    texcoord: vec2f,
-};
+#include "src/fullscreen.wgsl"
 
 @group(0) @binding(0)
 var input_texture_0: texture_2d<f32>;
@@ -16,11 +8,13 @@ var input_texture_sampler_0: sampler;
 var input_texture_1: texture_2d<f32>;
 @group(0) @binding(3)
 var input_texture_sampler_1: sampler;
+@group(0) @binding(4)
+var<uniform> weight: f32;
 
 @fragment
 fn main(in: VertexOutput) -> @location(0) vec4<f32> {
     let col_0 = textureSampleLevel(input_texture_0, input_texture_sampler_0, in.texcoord, 0.0);
     let col_1 = textureSampleLevel(input_texture_1, input_texture_sampler_1, in.texcoord, 0.0);
-    let col = col_0 + col_1 * 0.5;
+    let col = col_0 + col_1 * weight;
     return col;
 }
\ No newline at end of file
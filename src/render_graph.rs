@@ -0,0 +1,569 @@
+// a small render graph for the fullscreen passes after the scene
+// passes are declared with the textures they read and write, and the graph works out
+// the order to run them in, allocates every texture at its resolution and reallocates
+// them (and rebinds the passes reading them) when the output is resized
+//
+// textures no pass writes are inputs to the graph, something outside it (the scene)
+// renders into them before execute
+//
+// a history texture holds what another texture had in it at the end of the last execute,
+// so a pass can read its own previous output without the graph having a cycle in it
+//
+// scaled textures follow the render scale as well as the output size, for rendering
+// some of the graph at a lower resolution than the output
+
+use std::{any::Any, marker::PhantomData};
+
+use anyhow::bail;
+use glam::{uvec2, UVec2};
+
+use crate::gpu_profiler::GpuProfiler;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureHandle(usize);
+
+pub struct PassHandle<T> {
+    index: usize,
+    pass: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for PassHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PassHandle<T> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureSize {
+    // the size of the output
+    Full,
+    // the output size divided by this, at least 1 pixel
+    Fraction(u32),
+    // the output size times the render scale, at least 1 pixel
+    Scaled,
+}
+
+impl TextureSize {
+    pub fn resolve(&self, output_size: UVec2, render_scale: f32) -> UVec2 {
+        match self {
+            Self::Full => output_size,
+            Self::Fraction(divisor) => (output_size / *divisor).max(UVec2::ONE),
+            Self::Scaled => (output_size.as_vec2() * render_scale)
+                .round()
+                .as_uvec2()
+                .max(UVec2::ONE),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Texture(TextureHandle),
+    // the view given to execute, the surface or the headless output texture
+    Output,
+}
+
+pub trait GraphPass: Any {
+    // called once the graph is built and again whenever the textures it reads are
+    // reallocated, with their views in the order the inputs were declared
+    fn bind(&mut self, device: &wgpu::Device, inputs: &[&wgpu::TextureView]);
+
+    // the views of the declared outputs, in order
+    // timestamp_writes go on the pass, when it's being profiled
+    fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        outputs: &[&wgpu::TextureView],
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
+    );
+
+    // called with the shaders that changed on disk while hot reloading, see shader.rs
+    // passes using any of them build their pipelines again
+    fn reload_shaders(&mut self, _device: &wgpu::Device, _changed: &[String]) {}
+}
+
+struct GraphTexture {
+    label: String,
+    size: TextureSize,
+    format: wgpu::TextureFormat,
+    // on top of what the graph needs
+    usage: wgpu::TextureUsages,
+    // for history textures, the texture copied into this one after each execute
+    history_of: Option<TextureHandle>,
+    allocated: Option<(wgpu::Texture, wgpu::TextureView)>,
+}
+
+struct GraphNode {
+    label: String,
+    inputs: Vec<TextureHandle>,
+    outputs: Vec<Target>,
+    pass: Box<dyn GraphPass>,
+}
+
+pub struct RenderGraph {
+    textures: Vec<GraphTexture>,
+    nodes: Vec<GraphNode>,
+    // indices into nodes, filled in by build
+    order: Vec<usize>,
+    output_size: UVec2,
+    render_scale: f32,
+}
+
+impl RenderGraph {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            textures: vec![],
+            nodes: vec![],
+            order: vec![],
+            output_size: uvec2(width, height),
+            render_scale: 1.0,
+        }
+    }
+
+    pub fn add_texture(
+        &mut self,
+        label: &str,
+        size: TextureSize,
+        format: wgpu::TextureFormat,
+    ) -> TextureHandle {
+        self.textures.push(GraphTexture {
+            label: label.to_string(),
+            size,
+            format,
+            usage: wgpu::TextureUsages::empty(),
+            history_of: None,
+            allocated: None,
+        });
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    // for graph inputs written some other way than rendering, a compute shader for example
+    pub fn add_usage(&mut self, texture: TextureHandle, usage: wgpu::TextureUsages) {
+        self.textures[texture.0].usage |= usage;
+    }
+
+    // a texture with the contents texture had at the end of the previous execute
+    // it's cleared to zero when the graph is built or resized
+    pub fn add_history(&mut self, texture: TextureHandle) -> TextureHandle {
+        let source = &self.textures[texture.0];
+        self.textures.push(GraphTexture {
+            label: format!("{} history", source.label),
+            size: source.size,
+            format: source.format,
+            usage: wgpu::TextureUsages::empty(),
+            history_of: Some(texture),
+            allocated: None,
+        });
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    pub fn add_pass<T: GraphPass>(
+        &mut self,
+        label: &str,
+        inputs: &[TextureHandle],
+        outputs: &[Target],
+        pass: T,
+    ) -> PassHandle<T> {
+        self.nodes.push(GraphNode {
+            label: label.to_string(),
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            pass: Box::new(pass),
+        });
+        PassHandle {
+            index: self.nodes.len() - 1,
+            pass: PhantomData,
+        }
+    }
+
+    pub fn output_size(&self) -> UVec2 {
+        self.output_size
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    pub fn format(&self, texture: TextureHandle) -> wgpu::TextureFormat {
+        self.textures[texture.0].format
+    }
+
+    pub fn size(&self, texture: TextureHandle) -> UVec2 {
+        self.textures[texture.0]
+            .size
+            .resolve(self.output_size, self.render_scale)
+    }
+
+    // panics if the graph hasn't been built
+    pub fn view(&self, texture: TextureHandle) -> &wgpu::TextureView {
+        &self.textures[texture.0]
+            .allocated
+            .as_ref()
+            .expect("render graph textures are allocated by build")
+            .1
+    }
+
+    // panics if the graph hasn't been built
+    pub fn texture(&self, texture: TextureHandle) -> &wgpu::Texture {
+        &self.textures[texture.0]
+            .allocated
+            .as_ref()
+            .expect("render graph textures are allocated by build")
+            .0
+    }
+
+    pub fn pass<T: GraphPass>(&self, handle: PassHandle<T>) -> &T {
+        let pass: &dyn Any = self.nodes[handle.index].pass.as_ref();
+        pass.downcast_ref().expect("pass handles keep their type")
+    }
+
+    pub fn pass_mut<T: GraphPass>(&mut self, handle: PassHandle<T>) -> &mut T {
+        let pass: &mut dyn Any = self.nodes[handle.index].pass.as_mut();
+        pass.downcast_mut().expect("pass handles keep their type")
+    }
+
+    // every pass runs after the passes writing its inputs
+    // a texture can only have one writer, and only one pass can write to the output
+    fn schedule(&self) -> anyhow::Result<Vec<usize>> {
+        let mut writers = vec![None; self.textures.len()];
+        let mut output_writer = None;
+        for (index, node) in self.nodes.iter().enumerate() {
+            for target in &node.outputs {
+                if let Target::Texture(texture) = target {
+                    if self.textures[texture.0].history_of.is_some() {
+                        bail!(
+                            "\"{}\" writes to the history texture {}, only the graph writes those",
+                            node.label,
+                            self.target_label(*target)
+                        );
+                    }
+                }
+                let writer = match target {
+                    Target::Texture(texture) => &mut writers[texture.0],
+                    Target::Output => &mut output_writer,
+                };
+                if let Some(other) = writer.replace(index) {
+                    bail!(
+                        "\"{}\" and \"{}\" both write to {}",
+                        self.nodes[other].label,
+                        node.label,
+                        self.target_label(*target)
+                    );
+                }
+            }
+        }
+
+        let dependencies = self
+            .nodes
+            .iter()
+            .map(|node| {
+                node.inputs
+                    .iter()
+                    .filter_map(|texture| writers[texture.0])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // repeatedly take the first pass whose dependencies have all run
+        // so passes that don't depend on each other keep the order they were added in
+        let mut scheduled = vec![false; self.nodes.len()];
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while order.len() < self.nodes.len() {
+            let Some(next) = (0..self.nodes.len()).find(|&index| {
+                !scheduled[index]
+                    && dependencies[index]
+                        .iter()
+                        .all(|&dependency| scheduled[dependency])
+            }) else {
+                let stuck = (0..self.nodes.len())
+                    .filter(|&index| !scheduled[index])
+                    .map(|index| format!("\"{}\"", self.nodes[index].label))
+                    .collect::<Vec<_>>();
+                bail!("the passes {} depend on each other", stuck.join(", "));
+            };
+            scheduled[next] = true;
+            order.push(next);
+        }
+        Ok(order)
+    }
+
+    fn target_label(&self, target: Target) -> String {
+        match target {
+            Target::Texture(texture) => format!("\"{}\"", self.textures[texture.0].label),
+            Target::Output => "the output".to_string(),
+        }
+    }
+
+    // (re)allocates the textures of the sizes that should be
+    fn allocate(&mut self, device: &wgpu::Device, should_allocate: impl Fn(TextureSize) -> bool) {
+        let output_size = self.output_size;
+        let render_scale = self.render_scale;
+        let history_sources = self
+            .textures
+            .iter()
+            .filter_map(|texture| texture.history_of)
+            .collect::<Vec<_>>();
+        for (index, texture) in self.textures.iter_mut().enumerate() {
+            if !should_allocate(texture.size) {
+                continue;
+            }
+            let size = texture.size.resolve(output_size, render_scale);
+            let mut usage = texture.usage
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING;
+            if history_sources.contains(&TextureHandle(index)) {
+                usage |= wgpu::TextureUsages::COPY_SRC;
+            }
+            if texture.history_of.is_some() {
+                usage |= wgpu::TextureUsages::COPY_DST;
+            }
+            let allocated = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(&texture.label),
+                mip_level_count: 1,
+                size: wgpu::Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                format: texture.format,
+                dimension: wgpu::TextureDimension::D2,
+                usage,
+                sample_count: 1,
+                view_formats: &[],
+            });
+            let view = allocated.create_view(&wgpu::TextureViewDescriptor::default());
+            texture.allocated = Some((allocated, view));
+        }
+    }
+
+    fn bind(&mut self, device: &wgpu::Device) {
+        let textures = &self.textures;
+        for node in &mut self.nodes {
+            let inputs = node
+                .inputs
+                .iter()
+                .map(|texture| {
+                    &textures[texture.0]
+                        .allocated
+                        .as_ref()
+                        .expect("textures are allocated before binding")
+                        .1
+                })
+                .collect::<Vec<_>>();
+            node.pass.bind(device, &inputs);
+        }
+    }
+
+    // has to be called after the last pass is added and before execute
+    pub fn build(&mut self, device: &wgpu::Device) -> anyhow::Result<()> {
+        self.order = self.schedule()?;
+        self.allocate(device, |_| true);
+        self.bind(device);
+        Ok(())
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.output_size = uvec2(width, height);
+        self.allocate(device, |_| true);
+        self.bind(device);
+    }
+
+    // only reallocates the scaled textures, the rest keep their contents
+    pub fn set_render_scale(&mut self, device: &wgpu::Device, render_scale: f32) {
+        self.render_scale = render_scale;
+        self.allocate(device, |size| size == TextureSize::Scaled);
+        self.bind(device);
+    }
+
+    // see GraphPass::reload_shaders
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        for node in &mut self.nodes {
+            node.pass.reload_shaders(device, changed);
+        }
+    }
+
+    // each pass is timed under its label if there's a profiler
+    pub fn execute(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        mut profiler: Option<&mut GpuProfiler>,
+    ) {
+        for &index in &self.order {
+            let node = &self.nodes[index];
+            let outputs = node
+                .outputs
+                .iter()
+                .map(|target| match target {
+                    Target::Texture(texture) => self.view(*texture),
+                    Target::Output => output_view,
+                })
+                .collect::<Vec<_>>();
+            let timestamp_writes = profiler
+                .as_deref_mut()
+                .and_then(|profiler| profiler.render_pass(&node.label));
+            node.pass.render(encoder, &outputs, timestamp_writes);
+        }
+
+        for texture in &self.textures {
+            let Some(source) = texture.history_of else {
+                continue;
+            };
+            let size = texture.size.resolve(self.output_size, self.render_scale);
+            encoder.copy_texture_to_texture(
+                self.textures[source.0]
+                    .allocated
+                    .as_ref()
+                    .expect("render graph textures are allocated by build")
+                    .0
+                    .as_image_copy(),
+                texture
+                    .allocated
+                    .as_ref()
+                    .expect("render graph textures are allocated by build")
+                    .0
+                    .as_image_copy(),
+                wgpu::Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // passes are only scheduled here, nothing is rendered
+    struct NoPass;
+
+    impl GraphPass for NoPass {
+        fn bind(&mut self, _device: &wgpu::Device, _inputs: &[&wgpu::TextureView]) {}
+
+        fn render(
+            &self,
+            _encoder: &mut wgpu::CommandEncoder,
+            _outputs: &[&wgpu::TextureView],
+            _timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
+        ) {
+        }
+    }
+
+    fn texture(graph: &mut RenderGraph, label: &str) -> TextureHandle {
+        graph.add_texture(label, TextureSize::Full, wgpu::TextureFormat::Rgba16Float)
+    }
+
+    fn labels(graph: &RenderGraph) -> Vec<&str> {
+        graph
+            .schedule()
+            .unwrap()
+            .into_iter()
+            .map(|index| graph.nodes[index].label.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn passes_run_after_their_inputs_are_written() {
+        let mut graph = RenderGraph::new(64, 64);
+        let scene = texture(&mut graph, "scene");
+        let bright = texture(&mut graph, "bright");
+        let blurred = texture(&mut graph, "blurred");
+        graph.add_pass("composite", &[scene, blurred], &[Target::Output], NoPass);
+        graph.add_pass("blur", &[bright], &[Target::Texture(blurred)], NoPass);
+        graph.add_pass("threshold", &[scene], &[Target::Texture(bright)], NoPass);
+        assert_eq!(labels(&graph), ["threshold", "blur", "composite"]);
+    }
+
+    #[test]
+    fn independent_passes_keep_their_order() {
+        let mut graph = RenderGraph::new(64, 64);
+        let a = texture(&mut graph, "a");
+        let b = texture(&mut graph, "b");
+        graph.add_pass("second", &[], &[Target::Texture(b)], NoPass);
+        graph.add_pass("first", &[], &[Target::Texture(a)], NoPass);
+        graph.add_pass("both", &[a, b], &[Target::Output], NoPass);
+        assert_eq!(labels(&graph), ["second", "first", "both"]);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut graph = RenderGraph::new(64, 64);
+        let a = texture(&mut graph, "a");
+        let b = texture(&mut graph, "b");
+        graph.add_pass("outside", &[], &[Target::Output], NoPass);
+        graph.add_pass("ping", &[b], &[Target::Texture(a)], NoPass);
+        graph.add_pass("pong", &[a], &[Target::Texture(b)], NoPass);
+        let error = graph.schedule().unwrap_err().to_string();
+        assert_eq!(error, "the passes \"ping\", \"pong\" depend on each other");
+    }
+
+    #[test]
+    fn textures_have_one_writer() {
+        let mut graph = RenderGraph::new(64, 64);
+        let a = texture(&mut graph, "a");
+        graph.add_pass("first", &[], &[Target::Texture(a)], NoPass);
+        graph.add_pass("second", &[], &[Target::Texture(a)], NoPass);
+        let error = graph.schedule().unwrap_err().to_string();
+        assert_eq!(error, "\"first\" and \"second\" both write to \"a\"");
+
+        let mut graph = RenderGraph::new(64, 64);
+        graph.add_pass("first", &[], &[Target::Output], NoPass);
+        graph.add_pass("second", &[], &[Target::Output], NoPass);
+        let error = graph.schedule().unwrap_err().to_string();
+        assert_eq!(error, "\"first\" and \"second\" both write to the output");
+    }
+
+    #[test]
+    fn sizes_are_at_least_a_pixel() {
+        let output_size = uvec2(1920, 1080);
+        assert_eq!(TextureSize::Full.resolve(output_size, 0.5), output_size);
+        assert_eq!(
+            TextureSize::Fraction(4).resolve(output_size, 0.5),
+            uvec2(480, 270)
+        );
+        assert_eq!(
+            TextureSize::Fraction(2048).resolve(output_size, 1.0),
+            UVec2::ONE
+        );
+        assert_eq!(
+            TextureSize::Scaled.resolve(output_size, 0.5),
+            uvec2(960, 540)
+        );
+        assert_eq!(TextureSize::Scaled.resolve(uvec2(3, 3), 0.5), uvec2(2, 2));
+        assert_eq!(TextureSize::Scaled.resolve(output_size, 0.0), UVec2::ONE);
+    }
+
+    #[test]
+    fn history_is_read_before_it_is_written() {
+        let mut graph = RenderGraph::new(64, 64);
+        let scene = texture(&mut graph, "scene");
+        let accumulated = texture(&mut graph, "accumulated");
+        let history = graph.add_history(accumulated);
+        graph.add_pass("present", &[accumulated], &[Target::Output], NoPass);
+        graph.add_pass(
+            "accumulate",
+            &[scene, history],
+            &[Target::Texture(accumulated)],
+            NoPass,
+        );
+        assert_eq!(labels(&graph), ["accumulate", "present"]);
+    }
+
+    #[test]
+    fn history_is_only_written_by_the_graph() {
+        let mut graph = RenderGraph::new(64, 64);
+        let accumulated = texture(&mut graph, "accumulated");
+        let history = graph.add_history(accumulated);
+        graph.add_pass("overwrite", &[], &[Target::Texture(history)], NoPass);
+        let error = graph.schedule().unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "\"overwrite\" writes to the history texture \"accumulated history\", only the graph writes those"
+        );
+    }
+}
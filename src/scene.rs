@@ -1,8 +1,13 @@
 use crate::{
     camera::{Camera, CameraController},
+    cpu_tracer::TracerUniforms,
+    deflection_lut::DeflectionLut,
+    gpu_profiler::GpuProfiler,
+    hdr::HDR_FORMAT,
     indices::INDICES,
     otheruniforms::{BufferContent, IncValue, OtherUniform, OtherUniforms},
     podbool::PodBool,
+    shader::{self, Shader, Sources},
     texture::Texture,
     uniforms::CameraUniform,
     vertex::Vertex,
@@ -25,6 +30,100 @@ use cfg_if::cfg_if;
 
 use std::time::Duration;
 
+// how the scene's rays get marched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SceneBackend {
+    // a fragment shader over a screen triangle, works everywhere
+    #[default]
+    Fragment,
+    // a compute shader writing to storage textures a tile at a time
+    // webgl2 has no compute shaders, so there it falls back to Fragment
+    Compute,
+}
+
+impl SceneBackend {
+    pub const ALL: [Self; 2] = [Self::Fragment, Self::Compute];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Fragment => "fragment",
+            Self::Compute => "compute",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|backend| backend.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    // SCENE_BACKEND=compute cargo run picks it for the window
+    pub fn from_env() -> Self {
+        cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                Self::default()
+            } else {
+                std::env::var("SCENE_BACKEND")
+                    .ok()
+                    .and_then(|name| Self::from_name(&name))
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    // the webgl2 limits don't allow compute shaders at all
+    // so they're only raised when the compute backend will use them
+    pub fn required_limits(&self, adapter: &wgpu::Adapter) -> wgpu::Limits {
+        let compute_shaders = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+        let limits = if *self == Self::Compute && compute_shaders {
+            wgpu::Limits::downlevel_defaults()
+        } else {
+            wgpu::Limits::downlevel_webgl2_defaults()
+        };
+        limits.using_resolution(adapter.limits())
+    }
+
+    // whether it can write the scene's textures with the device, the compute shader
+    // only writes HDR_FORMAT
+    fn supported(&self, device: &wgpu::Device, format: wgpu::TextureFormat) -> bool {
+        match self {
+            Self::Fragment => true,
+            Self::Compute => {
+                let limits = device.limits();
+                limits.max_compute_workgroups_per_dimension > 0
+                    && limits.max_storage_textures_per_shader_stage >= 2
+                    && format == HDR_FORMAT
+            }
+        }
+    }
+
+    // what the scene's textures have to be usable as besides a render attachment
+    pub fn output_usage(&self) -> wgpu::TextureUsages {
+        match self {
+            Self::Fragment => wgpu::TextureUsages::empty(),
+            Self::Compute => wgpu::TextureUsages::STORAGE_BINDING,
+        }
+    }
+}
+
+// has to match TILE_SIZE in black_hole_compute.wgsl
+const COMPUTE_TILE_SIZE: u32 = 8;
+
+const BLACK_HOLE_SHADER: Shader = include_shader!("src/black_hole_maybe.wgsl");
+// includes BLACK_HOLE_SHADER
+const BLACK_HOLE_COMPUTE_SHADER: Shader = include_shader!("src/black_hole_compute.wgsl");
+
+struct ComputeBackend {
+    pipeline: wgpu::ComputePipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    // the storage textures, the scene's outputs change with resizes and graph rebuilds
+    // so this is bound every frame
+    output_bind_group_layout: wgpu::BindGroupLayout,
+}
+
 pub struct Scene {
     pub camera: Camera,
     pub camera_controller: CameraController,
@@ -32,14 +131,23 @@ pub struct Scene {
     pub camera_uniform: CameraUniform,
     pub camera_uniform_buffer: wgpu::Buffer,
 
-    pub other_uniforms: OtherUniforms<6>,
+    pub other_uniforms: OtherUniforms<20>,
     pub other_uniforms_buffer: wgpu::Buffer,
 
+    // only built while it's on
+    deflection_lut: DeflectionLut,
+
     pub bind_group: wgpu::BindGroup,
 
     pub space_texture_bind_group: wgpu::BindGroup,
 
     pub render_pipeline: wgpu::RenderPipeline,
+    // what render_pipeline is built with, for building it again
+    render_pipeline_layout: wgpu::PipelineLayout,
+    output_format: wgpu::TextureFormat,
+    render_blackout: bool,
+    // none for the fragment backend
+    compute: Option<ComputeBackend>,
 
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
@@ -47,6 +155,13 @@ pub struct Scene {
 
     pub resolution_uniform: UVec2,
     pub resolution_uniform_buffer: wgpu::Buffer,
+
+    // where in their pixel the rays go through, in pixels from -0.5 to 0.5
+    pub jitter: Vec2,
+    // stops the time, for anything that accumulates frames
+    pub paused: bool,
+    // whether anything that changes the image changed since take_changed
+    changed: bool,
 }
 
 impl Scene {
@@ -55,7 +170,29 @@ impl Scene {
         queue: &wgpu::Queue,
         config: &wgpu::SurfaceConfiguration,
         render_blackout: bool,
+        backend: SceneBackend,
     ) -> Self {
+        let backend = if backend.supported(device, config.format) {
+            backend
+        } else {
+            log::warn!(
+                "the {} backend isn't supported, falling back to the fragment backend",
+                backend.name()
+            );
+            SceneBackend::Fragment
+        };
+        // the compute pipeline reads the same uniforms and textures
+        let (uniform_visibility, fragment_visibility) = match backend {
+            SceneBackend::Fragment => (
+                wgpu::ShaderStages::VERTEX_FRAGMENT,
+                wgpu::ShaderStages::FRAGMENT,
+            ),
+            SceneBackend::Compute => (
+                wgpu::ShaderStages::VERTEX_FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+            ),
+        };
+
         let resolution_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             size: std::mem::size_of::<UVec2>() as wgpu::BufferAddress,
             label: Some("scene resolution_uniform_buffer"),
@@ -78,7 +215,7 @@ impl Scene {
         let camera_controller = CameraController::new(5.0, 0.5);
 
         let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update(&camera);
+        camera_uniform.update(&camera, Vec2::ZERO);
 
         let camera_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("camera uniforms"),
@@ -99,10 +236,11 @@ impl Scene {
                     }),
                 },
                 OtherUniform {
-                    label: "max delta time".into(),
+                    // error tolerance per step for the adaptive integrator, as a power of 10
+                    label: "integrator tolerance (log10)".into(),
                     inc_value: Box::new(IncValue {
-                        value: 0.5,
-                        inc: 0.02,
+                        value: -5.0,
+                        inc: 0.5,
                     }),
                 },
                 OtherUniform {
@@ -115,7 +253,7 @@ impl Scene {
                 OtherUniform {
                     label: "blackout event horizon".into(),
                     inc_value: Box::new(IncValue {
-                        value: PodBool::r#false(),
+                        value: PodBool::r#true(),
                         inc: PodBool::r#true(),
                     }),
                 },
@@ -133,6 +271,117 @@ impl Scene {
                         inc: 0.2,
                     }),
                 },
+                OtherUniform {
+                    label: "kerr metric".into(),
+                    inc_value: Box::new(IncValue {
+                        value: PodBool::r#false(),
+                        inc: PodBool::r#true(),
+                    }),
+                },
+                OtherUniform {
+                    // dimensionless, a / M, clamped to just under 1 in the shader
+                    label: "black hole spin".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 0.9,
+                        inc: 0.05,
+                    }),
+                },
+                OtherUniform {
+                    // 0 is fixed step rk4, 1 is adaptive rk45 (dormand-prince)
+                    // 2 is rk4 on the binet equation in the plane of each ray, schwarzschild only
+                    label: "integrator".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 0u32,
+                        inc: 1i32,
+                    }),
+                },
+                OtherUniform {
+                    // multiple of the isco, anything below 1 is treated as 1
+                    label: "disk inner radius (isco)".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 1.0,
+                        inc: 0.25,
+                    }),
+                },
+                OtherUniform {
+                    // multiple of the swartschild radius
+                    label: "disk outer radius".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 10.0,
+                        inc: 1.0,
+                    }),
+                },
+                OtherUniform {
+                    // relative to the rate that gives a 6500K peak around a non spinning black hole
+                    label: "accretion rate".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 1.0,
+                        inc: 0.1,
+                    }),
+                },
+                OtherUniform {
+                    // degrees, tilts the black hole and the disk about the x axis
+                    label: "disk inclination".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 0.0,
+                        inc: 5.0,
+                    }),
+                },
+                OtherUniform {
+                    label: "disk redshift and beaming".into(),
+                    inc_value: Box::new(IncValue {
+                        value: PodBool::r#true(),
+                        inc: PodBool::r#true(),
+                    }),
+                },
+                OtherUniform {
+                    // 4 for the total brightness, 3 for the brightness at a fixed frequency
+                    label: "beaming exponent".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 4.0,
+                        inc: 1.0,
+                    }),
+                },
+                OtherUniform {
+                    label: "volumetric disk".into(),
+                    inc_value: Box::new(IncValue {
+                        value: PodBool::r#false(),
+                        inc: PodBool::r#true(),
+                    }),
+                },
+                OtherUniform {
+                    // h / r
+                    label: "disk scale height".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 0.05,
+                        inc: 0.01,
+                    }),
+                },
+                OtherUniform {
+                    // 0 is smooth, 1 is fully broken up
+                    label: "disk turbulence".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 0.6,
+                        inc: 0.1,
+                    }),
+                },
+                OtherUniform {
+                    // absorption per unit length in the densest part of the gas
+                    label: "disk opacity".into(),
+                    inc_value: Box::new(IncValue {
+                        value: 4.0,
+                        inc: 0.5,
+                    }),
+                },
+                OtherUniform {
+                    // looks up where the rays go and what they hit instead of marching them
+                    // only for non spinning black holes, see deflection_lut.rs
+                    label: "deflection lookup table".into(),
+                    inc_value: Box::new(IncValue {
+                        value: PodBool::r#false(),
+                        inc: PodBool::r#true(),
+                    }),
+                },
             ],
         );
 
@@ -146,7 +395,7 @@ impl Scene {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    visibility: uniform_visibility,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -156,7 +405,7 @@ impl Scene {
                 },
                 wgpu::BindGroupLayoutEntry {
                     binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    visibility: fragment_visibility,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -193,12 +442,14 @@ impl Scene {
         }
         let space_texture = Texture::from_bytes(&device, &queue, space_bytes, "space").unwrap();
 
+        let deflection_lut = DeflectionLut::new(device, queue);
+
         let space_texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        visibility: fragment_visibility,
                         ty: wgpu::BindingType::Texture {
                             multisampled: false,
                             view_dimension: wgpu::TextureViewDimension::D2,
@@ -208,10 +459,30 @@ impl Scene {
                     },
                     wgpu::BindGroupLayoutEntry {
                         binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        visibility: fragment_visibility,
                         ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: fragment_visibility,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: fragment_visibility,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
                 ],
                 label: Some("space_bind_group_layout"),
             });
@@ -227,16 +498,18 @@ impl Scene {
                     binding: 1,
                     resource: wgpu::BindingResource::Sampler(&space_texture.sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&deflection_lut.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&deflection_lut.orbit_view),
+                },
             ],
             label: Some("space_bind_group"),
         });
 
-        // or include_wgsl!
-        let black_hole_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("black_hole_shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("./black_hole_maybe.wgsl").into()),
-        });
-
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("scene Pipeline Layout"),
@@ -244,9 +517,89 @@ impl Scene {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let render_pipeline = shader::build(device, "scene Pipeline", |sources| {
+            Self::create_render_pipeline(
+                device,
+                &render_pipeline_layout,
+                config.format,
+                render_blackout,
+                sources,
+            )
+        });
+
+        let compute = (backend == SceneBackend::Compute).then(|| {
+            Self::create_compute_backend(
+                device,
+                &[&bind_group_layout, &space_texture_bind_group_layout],
+                config.format,
+            )
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let num_indices = INDICES.len() as u32;
+
+        Self {
+            camera,
+            camera_controller,
+
+            camera_uniform,
+            camera_uniform_buffer,
+
+            other_uniforms,
+            other_uniforms_buffer,
+
+            deflection_lut,
+
+            bind_group,
+
+            space_texture_bind_group,
+
+            render_pipeline,
+            render_pipeline_layout,
+            output_format: config.format,
+            render_blackout,
+            compute,
+
+            vertex_buffer,
+            index_buffer,
+            num_indices,
+
+            resolution_uniform,
+            resolution_uniform_buffer,
+
+            jitter: Vec2::ZERO,
+            paused: false,
+            changed: true,
+        }
+    }
+
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        render_pipeline_layout: &wgpu::PipelineLayout,
+        output_format: wgpu::TextureFormat,
+        render_blackout: bool,
+        sources: Sources,
+    ) -> wgpu::RenderPipeline {
+        // or include_wgsl!
+        let black_hole_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("black_hole_shader"),
+            source: wgpu::ShaderSource::Wgsl(sources.get(BLACK_HOLE_SHADER)),
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("scene Pipeline"),
-            layout: Some(&render_pipeline_layout),
+            layout: Some(render_pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &black_hole_shader,
                 entry_point: "vs_main",
@@ -258,13 +611,13 @@ impl Scene {
                 entry_point: "fs_main",
                 targets: &[
                     Some(wgpu::ColorTargetState {
-                        format: config.format,
+                        format: output_format,
                         blend: Some(wgpu::BlendState::REPLACE),
                         write_mask: wgpu::ColorWrites::ALL,
                     }),
                     if render_blackout {
                         Some(wgpu::ColorTargetState {
-                            format: config.format,
+                            format: output_format,
                             blend: Some(wgpu::BlendState::REPLACE),
                             write_mask: wgpu::ColorWrites::ALL,
                         })
@@ -291,44 +644,106 @@ impl Scene {
             },
             multiview: None,
             cache: None,
-        });
+        })
+    }
 
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+    fn create_compute_backend(
+        device: &wgpu::Device,
+        scene_bind_group_layouts: &[&wgpu::BindGroupLayout],
+        format: wgpu::TextureFormat,
+    ) -> ComputeBackend {
+        let output_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            count: None,
+        };
+        let output_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("scene compute output bind_group_layout"),
+                entries: &[output_entry(0), output_entry(1)],
+            });
 
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(INDICES),
-            usage: wgpu::BufferUsages::INDEX,
+        let mut bind_group_layouts = scene_bind_group_layouts.to_vec();
+        bind_group_layouts.push(&output_bind_group_layout);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("scene compute Pipeline Layout"),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
         });
 
-        let num_indices = INDICES.len() as u32;
-
-        Self {
-            camera,
-            camera_controller,
-
-            camera_uniform,
-            camera_uniform_buffer,
-
-            other_uniforms,
-            other_uniforms_buffer,
+        let pipeline = shader::build(device, "scene compute Pipeline", |sources| {
+            Self::create_compute_pipeline(device, &pipeline_layout, sources)
+        });
 
-            bind_group,
+        ComputeBackend {
+            pipeline,
+            pipeline_layout,
+            output_bind_group_layout,
+        }
+    }
 
-            space_texture_bind_group,
+    fn create_compute_pipeline(
+        device: &wgpu::Device,
+        pipeline_layout: &wgpu::PipelineLayout,
+        sources: Sources,
+    ) -> wgpu::ComputePipeline {
+        // the entry point is in its own file so the fragment backend never compiles
+        // storage textures, which webgl2 can't
+        let black_hole_compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("black_hole_compute_shader"),
+            source: wgpu::ShaderSource::Wgsl(sources.get(BLACK_HOLE_COMPUTE_SHADER)),
+        });
 
-            render_pipeline,
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("scene compute Pipeline"),
+            layout: Some(pipeline_layout),
+            module: &black_hole_compute_shader,
+            entry_point: "cs_main",
+            compilation_options: Default::default(),
+            cache: None,
+        })
+    }
 
-            vertex_buffer,
-            index_buffer,
-            num_indices,
+    // see shader.rs
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if BLACK_HOLE_SHADER.changed_in(changed) {
+            let render_pipeline = shader::rebuild(device, "scene Pipeline", |sources| {
+                Self::create_render_pipeline(
+                    device,
+                    &self.render_pipeline_layout,
+                    self.output_format,
+                    self.render_blackout,
+                    sources,
+                )
+            });
+            if let Some(render_pipeline) = render_pipeline {
+                self.render_pipeline = render_pipeline;
+                self.changed = true;
+            }
+        }
+        if let Some(compute) = &mut self.compute {
+            if BLACK_HOLE_COMPUTE_SHADER.changed_in(changed) {
+                let pipeline = shader::rebuild(device, "scene compute Pipeline", |sources| {
+                    Self::create_compute_pipeline(device, &compute.pipeline_layout, sources)
+                });
+                if let Some(pipeline) = pipeline {
+                    compute.pipeline = pipeline;
+                    self.changed = true;
+                }
+            }
+        }
+    }
 
-            resolution_uniform,
-            resolution_uniform_buffer,
+    pub fn backend(&self) -> SceneBackend {
+        if self.compute.is_some() {
+            SceneBackend::Compute
+        } else {
+            SceneBackend::Fragment
         }
     }
 
@@ -379,16 +794,65 @@ impl Scene {
             Self::create_resolution(queue, config, &self.resolution_uniform_buffer);
 
         self.camera.aspect = config.width as f32 / config.height as f32;
+        self.changed = true;
+    }
+
+    // has to be called after other_uniforms is changed
+    pub fn write_other_uniforms(&mut self, queue: &wgpu::Queue) {
+        self.changed = true;
+        queue.write_buffer(
+            &self.other_uniforms_buffer,
+            0,
+            &self.other_uniforms.uniform_buffer_content(),
+        );
+        let deflection_lut = self
+            .other_uniforms
+            .value::<PodBool>("deflection lookup table")
+            .is_some_and(|value| value.get());
+        if deflection_lut {
+            self.deflection_lut.update(queue, &self.tracer_uniforms());
+        }
+    }
+
+    // other_uniforms as the cpu tracer has them
+    pub fn tracer_uniforms(&self) -> TracerUniforms {
+        let f32_value = |label| {
+            self.other_uniforms
+                .value::<f32>(label)
+                .expect("the scene has all of the tracer's uniforms")
+        };
+        let bool_value = |label| {
+            self.other_uniforms
+                .value::<PodBool>(label)
+                .expect("the scene has all of the tracer's uniforms")
+                .get()
+        };
+        TracerUniforms {
+            rs: f32_value("swartschild radius"),
+            log_tolerance: f32_value("integrator tolerance (log10)"),
+            blackout_eh: bool_value("blackout event horizon"),
+            max_dist: f32_value("max view distance"),
+            distortion_power: f32_value("distortion power"),
+            kerr: bool_value("kerr metric"),
+            spin: f32_value("black hole spin"),
+            integrator: self
+                .other_uniforms
+                .value::<u32>("integrator")
+                .expect("the scene has all of the tracer's uniforms")
+                .into(),
+            disk_inner_radius: f32_value("disk inner radius (isco)"),
+            disk_outer_radius: f32_value("disk outer radius"),
+            accretion_rate: f32_value("accretion rate"),
+            disk_inclination: f32_value("disk inclination"),
+            redshift: bool_value("disk redshift and beaming"),
+            beaming_exponent: f32_value("beaming exponent"),
+        }
     }
 
     pub fn process_event(&mut self, event: &WindowEvent, queue: &wgpu::Queue) -> bool {
         let other_uniforms_event_result = self.other_uniforms.process_event(event);
         if other_uniforms_event_result {
-            queue.write_buffer(
-                &self.other_uniforms_buffer,
-                0,
-                &self.other_uniforms.uniform_buffer_content(),
-            );
+            self.write_other_uniforms(queue);
         }
 
         [
@@ -448,7 +912,7 @@ impl Scene {
         cursor_position: Option<PhysicalPosition<f64>>,
         queue: &wgpu::Queue,
     ) {
-        self.camera_controller.update_camera(
+        let camera_moved = self.camera_controller.update_camera(
             &mut self.camera,
             delta_time,
             match (prev_cursor_position, cursor_position) {
@@ -459,20 +923,76 @@ impl Scene {
             },
         );
 
-        self.camera_uniform.update(&self.camera);
+        self.changed |= camera_moved;
+        self.changed |= self.deflection_lut.poll(queue);
+
+        if !self.paused {
+            self.camera_uniform.advance_time(delta_time);
+        }
+        self.write_camera_uniform(queue);
+    }
+
+    // blocks until the deflection lookup table has caught up with the uniforms
+    // for rendering to files, where there's no next frame to pick it up in
+    pub fn finish_deflection_lut(&mut self, queue: &wgpu::Queue) {
+        self.deflection_lut.wait(queue);
+    }
+
+    // jitter is in pixels, see Scene::jitter
+    pub fn set_jitter(&mut self, queue: &wgpu::Queue, jitter: Vec2) {
+        self.jitter = jitter;
+        self.write_camera_uniform(queue);
+    }
+
+    fn write_camera_uniform(&mut self, queue: &wgpu::Queue) {
+        // pixels go down and clip space goes up
+        let clip_jitter =
+            vec2(self.jitter.x, -self.jitter.y) * 2.0 / self.resolution_uniform.as_vec2();
+        self.camera_uniform.update(&self.camera, clip_jitter);
 
         let data = self.camera_uniform.uniform_buffer_content();
         queue.write_buffer(&self.camera_uniform_buffer, 0, &data);
     }
 
+    // for changes made from outside, like the camera being moved from the parameter panel
+    pub fn mark_changed(&mut self) {
+        self.changed = true;
+    }
+
+    // whether the image changed since the last call
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
     // renders the scene onto the given view(s)
     // if none are given, then the render will have no output
+    // the compute backend needs both, with the usage from SceneBackend::output_usage
+    // it's timed as "scene" if there's a profiler
     pub fn render(
         &self,
+        device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
         output_view: Option<&wgpu::TextureView>,
         blackout_output_view: Option<&wgpu::TextureView>,
+        profiler: Option<&mut GpuProfiler>,
     ) {
+        if let Some(compute) = &self.compute {
+            let (Some(output_view), Some(blackout_output_view)) = (output_view, blackout_output_view)
+            else {
+                panic!("the compute backend writes both of the scene's outputs");
+            };
+            let timestamp_writes = profiler.and_then(|profiler| profiler.compute_pass("scene"));
+            self.dispatch(
+                device,
+                encoder,
+                compute,
+                output_view,
+                blackout_output_view,
+                timestamp_writes,
+            );
+            return;
+        }
+
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("scene render_pass"),
             color_attachments: &[
@@ -504,7 +1024,7 @@ impl Scene {
                 }),
             ],
             depth_stencil_attachment: None,
-            timestamp_writes: None,
+            timestamp_writes: profiler.and_then(|profiler| profiler.render_pass("scene")),
             occlusion_query_set: None,
         });
 
@@ -520,4 +1040,43 @@ impl Scene {
         // render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
         render_pass.draw(0..3, 0..1);
     }
+    fn dispatch(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        compute: &ComputeBackend,
+        output_view: &wgpu::TextureView,
+        blackout_output_view: &wgpu::TextureView,
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
+        let output_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &compute.output_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(output_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(blackout_output_view),
+                },
+            ],
+            label: Some("scene compute output bind_group"),
+        });
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("scene compute_pass"),
+            timestamp_writes,
+        });
+        compute_pass.set_pipeline(&compute.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.set_bind_group(1, &self.space_texture_bind_group, &[]);
+        compute_pass.set_bind_group(2, &output_bind_group, &[]);
+        // one workgroup per TILE_SIZE square in black_hole_compute.wgsl
+        compute_pass.dispatch_workgroups(
+            self.resolution_uniform.x.div_ceil(COMPUTE_TILE_SIZE),
+            self.resolution_uniform.y.div_ceil(COMPUTE_TILE_SIZE),
+            1,
+        );
+    }
 }
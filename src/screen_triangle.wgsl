@@ -1,12 +1,4 @@
-struct VertexOutput {
-    // Mark output position as invariant so it's safe to use it with depth test Equal.
-    // Without @invariant, different usages in different render pipelines might optimize differently,
-    // causing slightly different results.
-    @invariant @builtin(position)
-    position: vec4f,
-    @location(0)
-    texcoord: vec2f,
-};
+#include "src/fullscreen.wgsl"
 
 var<private> positions: array<vec2f, 3> = array<vec2f, 3>(
     vec2f(3.0, 1.0),
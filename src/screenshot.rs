@@ -0,0 +1,403 @@
+// saving the scene as a png, re-rendered offscreen at the window's size or any other
+// it gets its own post processing graph at that size, with the window's bloom and tone mapping
+// every parameter goes into the png's text chunks so the image can be rendered again
+// the camera and post processing ones are named after the render binary's options
+// and the uniforms after their labels, for --set "<label>=<value>"
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    iter,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use anyhow::{bail, Context};
+use glam::{UVec2, Vec2};
+use half::f16;
+use image::{Rgba, Rgba32FImage, RgbaImage};
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{hdr::hdr_config, post_processing::PostProcessing, scene::Scene};
+
+// what tone mapping writes for a screenshot
+pub const SCREENSHOT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+// what shift + f12 uses until it's changed, 8k
+pub const DEFAULT_SCREENSHOT_SIZE: UVec2 = UVec2::new(7680, 4320);
+
+// a texture being copied back to the cpu
+pub struct TextureReadback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bytes_per_texel: u32,
+    format: wgpu::TextureFormat,
+}
+
+impl TextureReadback {
+    // records the copy into encoder, read has to be called after it's submitted
+    // the texture has to be 8 bit rgba or bgra for read, or Rgba16Float for read_hdr
+    pub fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> Self {
+        let width = texture.width();
+        let height = texture.height();
+
+        // rows in a buffer copy have to be a multiple of 256 bytes
+        let bytes_per_texel = texture
+            .format()
+            .block_copy_size(None)
+            .expect("readback textures have one aspect");
+        let unpadded_bytes_per_row = width * bytes_per_texel;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bytes_per_texel,
+            format: texture.format(),
+        }
+    }
+
+    // waits for the gpu
+    pub fn read(self, device: &wgpu::Device) -> anyhow::Result<RgbaImage> {
+        let bgra = match self.format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            format => bail!("Can't read a {:?} texture back as 8 bit rgba", format),
+        };
+        let mut image = RgbaImage::new(self.width, self.height);
+        self.read_texels(device, |x, y, texel| {
+            let pixel = if bgra {
+                [texel[2], texel[1], texel[0], texel[3]]
+            } else {
+                [texel[0], texel[1], texel[2], texel[3]]
+            };
+            image.put_pixel(x, y, Rgba(pixel));
+        })?;
+        Ok(image)
+    }
+
+    // waits for the gpu, the values aren't clipped or quantised to 8 bits
+    pub fn read_hdr(self, device: &wgpu::Device) -> anyhow::Result<Rgba32FImage> {
+        if self.format != wgpu::TextureFormat::Rgba16Float {
+            bail!("Can't read a {:?} texture back as hdr", self.format);
+        }
+        let mut image = Rgba32FImage::new(self.width, self.height);
+        self.read_texels(device, |x, y, texel| {
+            let mut pixel = [0.0; 4];
+            for (component, bytes) in pixel.iter_mut().zip(texel.chunks_exact(2)) {
+                *component = f16::from_le_bytes([bytes[0], bytes[1]]).to_f32();
+            }
+            image.put_pixel(x, y, Rgba(pixel));
+        })?;
+        Ok(image)
+    }
+
+    // calls texel with the position and bytes of every texel
+    fn read_texels(
+        self,
+        device: &wgpu::Device,
+        mut texel: impl FnMut(u32, u32, &[u8]),
+    ) -> anyhow::Result<()> {
+        let buffer_slice = self.buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .context("The readback buffer was never mapped")?
+            .context("Failed to map the readback buffer")?;
+
+        {
+            let data = buffer_slice.get_mapped_range();
+            let bytes_per_texel = self.bytes_per_texel as usize;
+            let unpadded_bytes_per_row = self.width as usize * bytes_per_texel;
+            for (y, row) in data
+                .chunks_exact(self.padded_bytes_per_row as usize)
+                .enumerate()
+            {
+                for (x, bytes) in row[..unpadded_bytes_per_row]
+                    .chunks_exact(bytes_per_texel)
+                    .enumerate()
+                {
+                    texel(x as u32, y as u32, bytes);
+                }
+            }
+        }
+        self.buffer.unmap();
+        Ok(())
+    }
+}
+
+fn vec3_to_string(v: glam::Vec3) -> String {
+    format!("{},{},{}", v.x, v.y, v.z)
+}
+
+// every parameter as a png text chunk keyword and its value
+pub fn parameters(scene: &Scene, post_processing: &PostProcessing) -> Vec<(String, String)> {
+    let camera = &scene.camera;
+    let bloom = post_processing.bloom.settings();
+    let tone_mapping = &post_processing.tone_mapping;
+    let mut parameters = vec![
+        ("Software".to_string(), env!("CARGO_PKG_NAME").to_string()),
+        ("pos".to_string(), vec3_to_string(camera.pos)),
+        ("dir".to_string(), vec3_to_string(camera.dir)),
+        ("up".to_string(), vec3_to_string(camera.up)),
+        ("fov".to_string(), camera.fovy.to_degrees().to_string()),
+        ("time".to_string(), scene.camera_uniform.time().to_string()),
+        ("backend".to_string(), scene.backend().name().to_string()),
+        ("bloom-levels".to_string(), bloom.levels.to_string()),
+        ("bloom-threshold".to_string(), bloom.threshold.to_string()),
+        ("bloom-intensity".to_string(), bloom.intensity.to_string()),
+        ("bloom-radius".to_string(), bloom.radius.to_string()),
+        (
+            "bloom-weights".to_string(),
+            (0..bloom.levels)
+                .map(|level| bloom.level_weight(level).to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        (
+            "tone-mapping".to_string(),
+            tone_mapping.curve.name().to_string(),
+        ),
+        ("exposure".to_string(), tone_mapping.exposure.to_string()),
+    ];
+    parameters.extend(
+        scene
+            .other_uniforms
+            .other_uniforms
+            .iter()
+            .map(|other_uniform| (other_uniform.label.clone(), other_uniform.value_to_string())),
+    );
+    parameters
+}
+
+// parameters go in as text chunks, see parameters
+pub fn save_png(
+    image: &RgbaImage,
+    path: impl AsRef<Path>,
+    parameters: &[(String, String)],
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let error = || format!("Failed to save {}", path.display());
+    let file = File::create(path).with_context(error)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (keyword, text) in parameters {
+        encoder
+            .add_text_chunk(keyword.clone(), text.clone())
+            .with_context(error)?;
+    }
+    let mut writer = encoder.write_header().with_context(error)?;
+    writer
+        .write_image_data(image.as_raw())
+        .with_context(error)?;
+    writer.finish().with_context(error)?;
+    Ok(())
+}
+
+// screenshot_0000.png, screenshot_0001.png, ... the first one that isn't there yet
+pub fn next_screenshot_path() -> PathBuf {
+    (0..)
+        .map(|number| PathBuf::from(format!("screenshot_{:04}.png", number)))
+        .find(|path| !path.exists())
+        .expect("there's a free screenshot number")
+}
+
+// renders the scene as it is now at width by height, with post_processing's settings
+// the scene goes back to its own size afterwards
+pub fn render(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    scene: &mut Scene,
+    post_processing: &PostProcessing,
+    width: u32,
+    height: u32,
+) -> anyhow::Result<RgbaImage> {
+    let max_size = device.limits().max_texture_dimension_2d;
+    if width == 0 || height == 0 || width > max_size || height > max_size {
+        bail!(
+            "A {}x{} screenshot can't be rendered, textures on this gpu go up to {}x{}",
+            width,
+            height,
+            max_size,
+            max_size
+        );
+    }
+
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: SCREENSHOT_FORMAT,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+        desired_maximum_frame_latency: 2,
+        alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        view_formats: vec![],
+    };
+    // without progressive, taa or dynamic resolution, there's only the one frame
+    let mut screenshot_post_processing = PostProcessing::new(
+        device,
+        &config,
+        post_processing.hdr_format(),
+        scene.backend().output_usage(),
+        post_processing.bloom.settings().clone(),
+    );
+    let tone_mapping = &mut screenshot_post_processing.tone_mapping;
+    tone_mapping.curve = post_processing.tone_mapping.curve;
+    tone_mapping.exposure = post_processing.tone_mapping.exposure;
+    tone_mapping.write_uniform(queue);
+
+    let output_texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("screenshot output texture"),
+        mip_level_count: 1,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        format: SCREENSHOT_FORMAT,
+        dimension: wgpu::TextureDimension::D2,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        sample_count: 1,
+        view_formats: &[],
+    });
+    let output_texture_view = output_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let scene_config = wgpu::SurfaceConfiguration {
+        width: scene.resolution_uniform.x,
+        height: scene.resolution_uniform.y,
+        ..config.clone()
+    };
+    let aspect = scene.camera.aspect;
+    let jitter = scene.jitter;
+    scene.resize(
+        device,
+        queue,
+        &hdr_config(&config, post_processing.hdr_format()),
+    );
+    scene.set_jitter(queue, Vec2::ZERO);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("screenshot encoder"),
+    });
+    scene.render(
+        device,
+        &mut encoder,
+        Some(screenshot_post_processing.scene_view()),
+        Some(screenshot_post_processing.scene_blackout_view()),
+        None,
+    );
+    screenshot_post_processing.render(&mut encoder, &output_texture_view, None);
+    let readback = TextureReadback::new(device, &mut encoder, &output_texture);
+    queue.submit(iter::once(encoder.finish()));
+    let image = readback.read(device);
+
+    scene.resize(
+        device,
+        queue,
+        &hdr_config(&scene_config, post_processing.hdr_format()),
+    );
+    scene.camera.aspect = aspect;
+    scene.set_jitter(queue, jitter);
+
+    image
+}
+
+// f12 asks for a screenshot at the window's size, shift + f12 at size
+pub struct ScreenshotController {
+    pub size: UVec2,
+    shift_pressed: bool,
+    // the size that was asked for, none for the window's
+    requested: Option<Option<UVec2>>,
+}
+
+impl ScreenshotController {
+    pub fn new() -> Self {
+        Self {
+            size: DEFAULT_SCREENSHOT_SIZE,
+            shift_pressed: false,
+            requested: None,
+        }
+    }
+
+    // none for the window's size
+    pub fn request(&mut self, size: Option<UVec2>) {
+        self.requested = Some(size);
+    }
+
+    // what was asked for since the last call
+    pub fn take_request(&mut self) -> Option<Option<UVec2>> {
+        self.requested.take()
+    }
+
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.shift_pressed = modifiers.state().shift_key();
+                false
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F12),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                self.request(self.shift_pressed.then_some(self.size));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Default for ScreenshotController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,40 @@
+// signed distance functions and rotations for ray marching
+
+fn sdf_sphere(p: vec3<f32>, centre: vec3<f32>, r: f32) -> f32 {
+    return length(centre - p) - r;
+}
+
+fn sdf_plane(p: vec3<f32>, y: f32) -> f32 {
+    return abs(p.y - y) - 0.02;
+}
+
+fn sdf_cylinder(p: vec3<f32>, pos: vec2<f32>, radius: f32) -> f32 {
+    return length(p.xz - pos) - radius;
+}
+
+fn sdf_accretion_disk(p: vec3<f32>, centre: vec3<f32>, big_r: f32, little_r: f32) -> f32 {
+    return max(max(sdf_cylinder(p, centre.xz, big_r), -sdf_cylinder(p, centre.xz, little_r)), sdf_plane(p, centre.y));
+}
+
+fn rotate_vector(vector: vec3f, unit_axis: vec3f, angle: f32) -> vec3f {
+    let cos_theta = cos(angle);
+    let sin_theta = sin(angle);
+    // Calculate the rotation matrix components
+    let ux = unit_axis.x;
+    let uy = unit_axis.y;
+    let uz = unit_axis.z;
+    let one_minus_cos = 1.0 - cos_theta;
+
+    // Apply the rotation formula
+    let x = vector.x * (cos_theta + ux * ux * one_minus_cos) + 
+            vector.y * (ux * uy * one_minus_cos - uz * sin_theta) + 
+            vector.z * (ux * uz * one_minus_cos + uy * sin_theta);
+    let y = vector.x * (uy * ux * one_minus_cos + uz * sin_theta) + 
+            vector.y * (cos_theta + uy * uy * one_minus_cos) + 
+            vector.z * (uy * uz * one_minus_cos - ux * sin_theta);
+    let z = vector.x * (uz * ux * one_minus_cos - uy * sin_theta) + 
+            vector.y * (uz * uy * one_minus_cos + ux * sin_theta) + 
+            vector.z * (cos_theta + uz * uz * one_minus_cos);
+
+    return vec3<f32>(x, y, z);
+}
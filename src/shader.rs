@@ -0,0 +1,413 @@
+// the wgsl files, embedded in the binary and read again from disk while hot reloading
+// SHADER_HOT_RELOAD=1 cargo run watches src, and when a shader in it changes everything using it
+// builds its pipelines again from the file, see Scene::reload_shaders and GraphPass::reload_shaders
+// if they don't validate the error is logged and the last pipelines that did stay in use
+//
+// before they're built the shaders go through a small preprocessor, with a directive on each line starting with #
+//   #include "src/sdf.wgsl"  pastes in a file from INCLUDABLE, only the first time it's included in a shader
+//   #define NAME             for #ifdef and #ifndef in the rest of the shader and what it includes
+//   #ifdef NAME, #ifndef NAME, #else and #endif  keep the lines between them or not, and can be nested
+// includes are read from the same place as the shader, so editing one while hot reloading
+// rebuilds everything that includes it
+
+use std::{
+    borrow::Cow,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{bail, Context};
+
+// set once a ShaderWatcher is running
+static HOT_RELOADING: AtomicBool = AtomicBool::new(false);
+
+pub fn hot_reloading() -> bool {
+    HOT_RELOADING.load(Ordering::Relaxed)
+}
+
+// the files #include can name, they're embedded like every other shader
+const INCLUDABLE: &[Shader] = &[
+    include_shader!("src/fullscreen.wgsl"),
+    include_shader!("src/sdf.wgsl"),
+    include_shader!("src/integrator.wgsl"),
+    include_shader!("src/colour.wgsl"),
+    include_shader!("src/black_hole_maybe.wgsl"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shader {
+    // from the crate's root, like src/copy.wgsl
+    pub path: &'static str,
+    embedded: &'static str,
+}
+
+impl Shader {
+    // include_shader! embeds the file as well
+    pub const fn new(path: &'static str, embedded: &'static str) -> Self {
+        Self { path, embedded }
+    }
+
+    // what's on disk now, none if it can't be read, which is logged
+    pub fn read(&self) -> Option<String> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(self.path);
+        match std::fs::read_to_string(&path) {
+            Ok(source) => Some(source),
+            Err(error) => {
+                log::error!("couldn't read {}: {}", path.display(), error);
+                None
+            }
+        }
+    }
+
+    // whether it or anything it includes is one of the paths from ShaderWatcher::changed
+    pub fn changed_in(&self, changed: &[String]) -> bool {
+        let mut preprocessor = Preprocessor::new(Sources::Current);
+        // the files up to an error are still there, and the error is logged when it's built
+        let _ = preprocessor.include(*self);
+        preprocessor
+            .files
+            .iter()
+            .any(|file| changed.iter().any(|path| path == file))
+    }
+}
+
+// where pipelines get their shaders from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sources {
+    // the ones in the binary
+    Embedded,
+    // from disk while hot reloading, so passes made after a reload get it too
+    Current,
+}
+
+impl Sources {
+    // the shader with its directives resolved, ready for create_shader_module
+    pub fn get(&self, shader: Shader) -> Cow<'static, str> {
+        let mut preprocessor = Preprocessor::new(*self);
+        match preprocessor.include(shader) {
+            Ok(()) => preprocessor.output.into(),
+            // the embedded shaders are always run before they're committed
+            Err(error) if *self == Self::Embedded => panic!("{:#}", error),
+            // an empty module, so whatever it's built into doesn't validate
+            Err(error) => {
+                log::error!("{:#}", error);
+                "".into()
+            }
+        }
+    }
+
+    // one file as it is, without preprocessing
+    fn file(&self, shader: Shader) -> Cow<'static, str> {
+        if *self == Self::Current && hot_reloading() {
+            if let Some(source) = shader.read() {
+                return source.into();
+            }
+        }
+        shader.embedded.into()
+    }
+}
+
+struct Preprocessor {
+    sources: Sources,
+    defines: Vec<String>,
+    // every file included so far, in the order they were started
+    files: Vec<&'static str>,
+    output: String,
+}
+
+impl Preprocessor {
+    fn new(sources: Sources) -> Self {
+        Self {
+            sources,
+            defines: vec![],
+            files: vec![],
+            output: String::new(),
+        }
+    }
+
+    fn include(&mut self, shader: Shader) -> anyhow::Result<()> {
+        if self.files.contains(&shader.path) {
+            return Ok(());
+        }
+        self.files.push(shader.path);
+
+        let source = self.sources.file(shader);
+        // one for each #ifdef or #ifndef the line is in, whether its condition held
+        let mut conditions: Vec<bool> = vec![];
+        for (index, line) in source.lines().enumerate() {
+            let location = || format!("{}:{}", shader.path, index + 1);
+            let kept = conditions.iter().all(|&condition| condition);
+            let Some(directive) = line.trim().strip_prefix('#') else {
+                if kept {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+                continue;
+            };
+            let (name, argument) = directive
+                .split_once(char::is_whitespace)
+                .map(|(name, argument)| (name, argument.trim()))
+                .unwrap_or((directive, ""));
+            let needs_argument = || {
+                if argument.is_empty() {
+                    bail!("{}: #{} needs an argument", location(), name);
+                }
+                Ok(argument)
+            };
+            match name {
+                "ifdef" | "ifndef" => {
+                    let argument = needs_argument()?;
+                    let defined = self.defines.iter().any(|define| define == argument);
+                    conditions.push(defined == (name == "ifdef"));
+                }
+                "else" => {
+                    let condition = conditions
+                        .last_mut()
+                        .with_context(|| format!("{}: #else without #ifdef", location()))?;
+                    *condition = !*condition;
+                }
+                "endif" => {
+                    conditions
+                        .pop()
+                        .with_context(|| format!("{}: #endif without #ifdef", location()))?;
+                }
+                _ if !kept => {}
+                "define" => self.defines.push(needs_argument()?.to_string()),
+                "include" => {
+                    let path = needs_argument()?
+                        .strip_prefix('"')
+                        .and_then(|path| path.strip_suffix('"'))
+                        .with_context(|| format!("{}: the path has to be in quotes", location()))?;
+                    let included = INCLUDABLE
+                        .iter()
+                        .find(|included| included.path == path)
+                        .with_context(|| {
+                            format!("{}: {} isn't in shader::INCLUDABLE", location(), path)
+                        })?;
+                    self.include(*included)
+                        .with_context(|| format!("included from {}", location()))?;
+                }
+                _ => bail!("{}: unknown directive #{}", location(), name),
+            }
+        }
+        if !conditions.is_empty() {
+            bail!("{}: #ifdef without #endif", shader.path);
+        }
+        Ok(())
+    }
+}
+
+// runs create with wgpu's validation errors returned instead of panicking
+fn validated<T>(device: &wgpu::Device, create: impl FnOnce() -> T) -> Result<T, wgpu::Error> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let created = create();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(error),
+        None => Ok(created),
+    }
+}
+
+// builds something with the current sources, which can be a broken file while hot reloading
+// then it's logged and built with the embedded ones
+pub fn build<T>(device: &wgpu::Device, label: &str, create: impl Fn(Sources) -> T) -> T {
+    if !hot_reloading() {
+        return create(Sources::Embedded);
+    }
+    validated(device, || create(Sources::Current)).unwrap_or_else(|error| {
+        log::error!(
+            "{} didn't build, using the embedded shaders: {}",
+            label,
+            error
+        );
+        create(Sources::Embedded)
+    })
+}
+
+// builds something again after its shaders changed, none if it didn't validate, which is logged
+pub fn rebuild<T>(device: &wgpu::Device, label: &str, create: impl Fn(Sources) -> T) -> Option<T> {
+    validated(device, || create(Sources::Current))
+        .map_err(|error| {
+            log::error!(
+                "{} didn't build, keeping the last one that did: {}",
+                label,
+                error
+            );
+        })
+        .ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ShaderWatcher {
+    // the events stop when this is dropped
+    _watcher: notify::RecommendedWatcher,
+    events: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ShaderWatcher {
+    pub fn new() -> anyhow::Result<Self> {
+        use notify::Watcher;
+
+        let (sender, events) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        watcher.watch(&src, notify::RecursiveMode::NonRecursive)?;
+        HOT_RELOADING.store(true, Ordering::Relaxed);
+        println!("watching {} for shader changes", src.display());
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    // SHADER_HOT_RELOAD=1 cargo run turns it on for the window
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("SHADER_HOT_RELOAD")?;
+        Self::new()
+            .map_err(|error| {
+                log::warn!(
+                    "SHADER_HOT_RELOAD is set but the shaders can't be watched: {:#}",
+                    error
+                );
+            })
+            .ok()
+    }
+
+    // the shaders written since the last call, as paths like Shader::path
+    pub fn changed(&self) -> Vec<String> {
+        let mut changed = vec![];
+        for event in self.events.try_iter().flatten() {
+            if !matches!(
+                event.kind,
+                notify::EventKind::Create(_) | notify::EventKind::Modify(_)
+            ) {
+                continue;
+            }
+            for path in event.paths {
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "wgsl")
+                {
+                    if let Some(file_name) = path.file_name() {
+                        let path = format!("src/{}", file_name.to_string_lossy());
+                        if !changed.contains(&path) {
+                            changed.push(path);
+                        }
+                    }
+                }
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preprocess(source: &'static str) -> anyhow::Result<String> {
+        let mut preprocessor = Preprocessor::new(Sources::Embedded);
+        preprocessor.include(Shader::new("src/test.wgsl", source))?;
+        Ok(preprocessor.output)
+    }
+
+    fn error(source: &'static str) -> String {
+        format!("{:#}", preprocess(source).unwrap_err())
+    }
+
+    #[test]
+    fn conditions_nest() {
+        let source = "\
+#define A
+#ifdef A
+a
+#ifdef B
+a and b
+#else
+a without b
+#endif
+#else
+#ifndef B
+neither
+#endif
+#endif
+after
+";
+        assert_eq!(preprocess(source).unwrap(), "a\na without b\nafter\n");
+    }
+
+    #[test]
+    fn defines_in_skipped_lines_are_ignored() {
+        let source = "\
+#ifdef A
+#define B
+#include \"src/colour.wgsl\"
+#endif
+#ifdef B
+b
+#else
+no b
+#endif
+";
+        assert_eq!(preprocess(source).unwrap(), "no b\n");
+    }
+
+    #[test]
+    fn unmatched_directives_are_errors() {
+        assert_eq!(error("#endif\n"), "src/test.wgsl:1: #endif without #ifdef");
+        assert_eq!(error("a\n#else\n"), "src/test.wgsl:2: #else without #ifdef");
+        assert_eq!(
+            error("#ifdef A\n#ifdef B\n#endif\n"),
+            "src/test.wgsl: #ifdef without #endif"
+        );
+        assert_eq!(
+            error("#ifdef\n"),
+            "src/test.wgsl:1: #ifdef needs an argument"
+        );
+        assert_eq!(error("#if A\n"), "src/test.wgsl:1: unknown directive #if");
+    }
+
+    #[test]
+    fn files_are_only_included_once() {
+        let colour = include_shader!("src/colour.wgsl");
+        let once = preprocess("#include \"src/colour.wgsl\"\n").unwrap();
+        assert_eq!(once, colour.embedded);
+        let twice =
+            preprocess("#include \"src/colour.wgsl\"\n#include \"src/colour.wgsl\"\n").unwrap();
+        assert_eq!(twice, once);
+    }
+
+    #[test]
+    fn includes_have_to_be_includable() {
+        assert_eq!(
+            error("#include \"src/missing.wgsl\"\n"),
+            "src/test.wgsl:1: src/missing.wgsl isn't in shader::INCLUDABLE"
+        );
+        assert_eq!(
+            error("#include src/colour.wgsl\n"),
+            "src/test.wgsl:1: the path has to be in quotes"
+        );
+    }
+
+    // Sources::get panics if an embedded shader doesn't preprocess
+    #[test]
+    fn every_shader_preprocesses() {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut count = 0;
+        for entry in std::fs::read_dir(src).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("wgsl") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            let path = format!("src/{}", path.file_name().unwrap().to_string_lossy());
+            let shader = Shader::new(Box::leak(path.into()), Box::leak(source.into()));
+            let mut preprocessor = Preprocessor::new(Sources::Embedded);
+            if let Err(error) = preprocessor.include(shader) {
+                panic!("{:#}", error);
+            }
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+}
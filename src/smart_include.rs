@@ -11,4 +11,12 @@ macro_rules! smart_include_bytes {
     ($path:literal) => {
         include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path))
     };
-}
\ No newline at end of file
+}
+
+// a wgsl file from the crate's root that can be read again while hot reloading, see shader.rs
+#[macro_export]
+macro_rules! include_shader {
+    ($path:literal) => {
+        $crate::shader::Shader::new($path, $crate::smart_include_str!($path))
+    };
+}
@@ -7,11 +7,17 @@ use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::Fullscreen;
 use winit::{event::*, window::Window};
 
-use crate::bloom::Bloom;
+use crate::bloom::BloomSettings;
+use crate::dynamic_resolution::{FrameTimer, DEFAULT_TARGET_FRAME_RATE};
+use crate::gpu_profiler::GpuProfiler;
+use crate::parameter_panel::ParameterPanel;
+use crate::screenshot::{self, ScreenshotController};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::shader::ShaderWatcher;
+use crate::hdr::{hdr_config, hdr_format};
 // use crate::bloom::Bloom;
 // use crate::downsampling::{self, Downsampling};
 // use crate::gaussian_blur::GaussianBlur;
-use crate::kawase_downsampling::KawaseDownsampling;
 use crate::time_replacement::{Duration, Instant};
 // use crate::upsampling::Upsampling;
 use std::thread::sleep;
@@ -21,13 +27,15 @@ use wasm_bindgen::prelude::*;
 
 use crate::settings::{Settings, SettingsController};
 
-use crate::scene::Scene;
+use crate::post_processing::PostProcessing;
+use crate::scene::{Scene, SceneBackend};
 
 pub struct State<'a> {
     // wgpu and winit setup
     pub window: Arc<Window>,
     pub surface: Surface<'a>,
-    pub device: Device,
+    // shared with the frame timer's thread
+    pub device: Arc<Device>,
     pub queue: Queue,
     pub config: SurfaceConfiguration,
 
@@ -37,7 +45,17 @@ pub struct State<'a> {
 
     pub scene: Scene,
     // pub blur: Blur,
-    pub bloom: Bloom,
+    pub post_processing: PostProcessing,
+    // none unless GPU_PROFILE is set, see GpuProfiler::from_env
+    pub profiler: Option<GpuProfiler>,
+    // how long the frames take on the gpu, for dynamic resolution
+    pub frame_timer: FrameTimer,
+    // drawn over everything, f1 toggles it
+    pub parameter_panel: ParameterPanel,
+    pub screenshot_controller: ScreenshotController,
+    // none unless SHADER_HOT_RELOAD is set, see ShaderWatcher::from_env
+    #[cfg(not(target_arch = "wasm32"))]
+    pub shader_watcher: Option<ShaderWatcher>,
     // pub downsampling: Downsampling<{ LEVELS }>,
     // pub upsampling: Upsampling<{ LEVELS }>,
 
@@ -77,21 +95,23 @@ impl State<'_> {
             .await
             .expect("Failed to find an appropriate adapter");
 
+        let backend = SceneBackend::from_env();
+
         // Create the logical device and command queue
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: wgpu::Features::empty(),
+                    required_features: GpuProfiler::features(&adapter),
                     // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
-                    required_limits: wgpu::Limits::downlevel_webgl2_defaults()
-                        .using_resolution(adapter.limits()),
+                    required_limits: backend.required_limits(&adapter),
                     memory_hints: wgpu::MemoryHints::MemoryUsage,
                 },
                 None,
             )
             .await
             .expect("Failed to create device");
+        let device = Arc::new(device);
 
         let swapchain_capabilities = surface.get_capabilities(&adapter);
         let swapchain_format = swapchain_capabilities.formats[0];
@@ -107,7 +127,19 @@ impl State<'_> {
 
         surface.configure(&device, &config);
 
-        let scene = Scene::new(&device, &queue, &config, true);
+        let hdr_format = hdr_format(&adapter);
+
+        // before anything builds a pipeline, so they all start from the files on disk
+        #[cfg(not(target_arch = "wasm32"))]
+        let shader_watcher = ShaderWatcher::from_env();
+
+        let scene = Scene::new(
+            &device,
+            &queue,
+            &hdr_config(&config, hdr_format),
+            true,
+            backend,
+        );
 
         // let blur = Blur::new(&device, &queue, &config, &scene.output_texture_view);
 
@@ -122,7 +154,20 @@ impl State<'_> {
         // let kawase_downsampling = KawaseDownsampling::new(&device, &config);
         // let kawase_upsampling = KawaseUpsampling::new(&device, &config);
 
-        let bloom = Bloom::new(&device, &config, 3);
+        let post_processing =
+            PostProcessing::new(
+                &device,
+                &config,
+                hdr_format,
+                scene.backend().output_usage(),
+                BloomSettings::default(),
+            );
+
+        let profiler = GpuProfiler::from_env(&device, &queue);
+        let frame_timer = FrameTimer::new(device.clone());
+
+        let parameter_panel = ParameterPanel::new(&device, &window, config.format);
+        let screenshot_controller = ScreenshotController::new();
 
         let last_frame_time = Instant::now();
 
@@ -151,7 +196,13 @@ impl State<'_> {
             // kawase_upsampling,
             // kawase_downsampling,
 
-            bloom,
+            post_processing,
+            profiler,
+            frame_timer,
+            parameter_panel,
+            screenshot_controller,
+            #[cfg(not(target_arch = "wasm32"))]
+            shader_watcher,
 
             start_of_last_frame_instant: last_frame_time,
             delta_time,
@@ -180,15 +231,35 @@ impl State<'_> {
 
             // self.kawase_downsampling.resize(&self.device, &self.config, &self.queue);
             // self.kawase_upsampling.resize(&self.device, &self.config, &self.queue);
-            self.bloom.resize(&self.device, &self.config, &self.queue);
+            self.post_processing
+                .resize(&self.device, new_size.width, new_size.height);
 
             // self.gaussian_blur.resize(&self.device, &self.config);
         }
     }
 
+    // the scene renders at the render scale rather than the surface size
+    fn resize_scene(&mut self) {
+        let scene_size = self.post_processing.scene_size();
+        let scene_config = SurfaceConfiguration {
+            width: scene_size.x,
+            height: scene_size.y,
+            ..self.config.clone()
+        };
+        self.scene.resize(&self.device, &self.queue, &scene_config);
+        // it gets stretched over the whole surface, so it keeps the surface's aspect
+        self.scene.camera.aspect = self.config.width as f32 / self.config.height as f32;
+    }
+
     pub fn process_event(&mut self, event: &WindowEvent) -> bool {
-        self.scene.process_event(event, &self.queue);
-        self.settings_controller.process_event(event);
+        // typing into the panel or dragging in it shouldn't move the camera or change anything else
+        if !self.parameter_panel.process_event(&self.window, event) {
+            self.scene.process_event(event, &self.queue);
+            self.settings_controller.process_event(event);
+            self.post_processing
+                .process_event(event, &self.device, &self.queue);
+            self.screenshot_controller.process_event(event);
+        }
         match event {
             WindowEvent::Resized(new_size) => {
                 self.resize(new_size);
@@ -243,19 +314,86 @@ impl State<'_> {
         self.start_of_last_frame_instant += self.delta_time;
         // update controllers
         self.settings_controller.update_settings(&mut self.settings);
+        self.parameter_panel.update(
+            &self.window,
+            &self.device,
+            &self.queue,
+            &mut self.scene,
+            &mut self.post_processing,
+            &mut self.settings,
+            &mut self.screenshot_controller,
+        );
+        if let Some(size) = self.screenshot_controller.take_request() {
+            self.screenshot(size);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.reload_shaders();
+        // turning dynamic resolution on or off, or resizing, changes the scene's size
+        if self.post_processing.scene_size() != self.scene.resolution_uniform {
+            self.resize_scene();
+        }
+        // the disk can't keep turning while frames are averaged
+        self.scene.paused = self.post_processing.progressive.enabled();
         self.scene.update(
             self.delta_time,
             self.prev_cursor_position,
             self.cursor_position,
             &self.queue,
         );
+        let changed = self.scene.take_changed();
+        let jitter = self
+            .post_processing
+            .next_frame(&self.queue, &self.scene.camera, changed);
+        self.scene.set_jitter(&self.queue, jitter);
         self.prev_cursor_position = self.cursor_position;
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let render_start = Instant::now();
+    // rebuilds whatever uses the shaders that changed on disk since the last frame
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_shaders(&mut self) {
+        let Some(shader_watcher) = &self.shader_watcher else {
+            return;
+        };
+        let changed = shader_watcher.changed();
+        if changed.is_empty() {
+            return;
+        }
+        println!("reloading {}", changed.join(", "));
+        self.scene.reload_shaders(&self.device, &changed);
+        self.post_processing.reload_shaders(&self.device, &changed);
+    }
+
+    // none for the window's size
+    // there's nothing to save to on the web, so it only logs the error there
+    pub fn screenshot(&mut self, size: Option<glam::UVec2>) {
+        let size = size.unwrap_or(uvec2(self.config.width, self.config.height));
+        println!("rendering a {}x{} screenshot", size.x, size.y);
+        let path = screenshot::next_screenshot_path();
+        let result = screenshot::render(
+            &self.device,
+            &self.queue,
+            &mut self.scene,
+            &self.post_processing,
+            size.x,
+            size.y,
+        )
+        .and_then(|image| {
+            screenshot::save_png(
+                &image,
+                &path,
+                &screenshot::parameters(&self.scene, &self.post_processing),
+            )
+        });
+        match result {
+            Ok(()) => println!("saved {}", path.display()),
+            Err(error) => log::error!("{:#}", error),
+        }
+    }
 
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
+        // after getting the surface texture, which waits for vsync
+        let render_start = Instant::now();
         let output_view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -267,15 +405,21 @@ impl State<'_> {
                 label: Some("scene Render Encoder"),
             });
 
+        if let Some(profiler) = &mut self.profiler {
+            profiler.begin_frame();
+        }
+
         self.scene.render(
+            &self.device,
             &mut encoder,
             // None,
-            Some(&self.bloom.full_image_input_texture_view()),
+            Some(self.post_processing.scene_view()),
             // Some(&self.bloom.input_texture_view()),
-            Some(&self.bloom.blackout_input_texture_view()),
+            Some(self.post_processing.scene_blackout_view()),
             // Some(self.bloom.input_texture_view()),
             // None,
             // Some(&output_view),
+            self.profiler.as_mut(),
         );
 
         // self.kawase_downsampling.render(&mut encoder, Some(self.kawase_upsampling.input_texture_view()));
@@ -283,20 +427,59 @@ impl State<'_> {
 
         // self.gaussian_blur.render(&mut encoder, Some(&output_view));
 
-        self.bloom.render(&mut encoder, Some(&output_view));
+        self.post_processing
+            .render(&mut encoder, &output_view, self.profiler.as_mut());
+        self.parameter_panel.render(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &output_view,
+            [self.config.width, self.config.height],
+            self.profiler.as_mut(),
+        );
+        if let Some(profiler) = &self.profiler {
+            profiler.resolve(&mut encoder);
+        }
         // self.downsampling
         // .render(&mut encoder, Some(self.upsampling.input_texture_view()));
         // self.downsampling.render(&mut encoder, Some(&output_view));
         // self.upsampling.render(&mut encoder, Some(&output_view));
         // self.upsampling.render(&mut encoder, Some(&output_view));
 
-        self.queue.submit(iter::once(encoder.finish()));
+        let submission = self.queue.submit(iter::once(encoder.finish()));
+        // submitting doesn't wait for the gpu, dynamic resolution gets the time it took later
+        if self.post_processing.dynamic_resolution.enabled() {
+            self.frame_timer
+                .submitted(&self.queue, submission, render_start);
+        }
+        // this waits for it too
+        if let Some(profiler) = &mut self.profiler {
+            profiler.read(&self.device);
+        }
 
         output.present();
 
         let render_time = Instant::now() - render_start;
         if self.frame_number % 100 == 0 {
             dbg!(render_time);
+            if let Some(profiler) = &self.profiler {
+                log::info!("gpu time per pass:\n{}", profiler.table());
+            }
+        }
+
+        let target_frame_time = Duration::from_secs_f32(
+            1.0 / self
+                .settings
+                .max_frame_rate
+                .unwrap_or(DEFAULT_TARGET_FRAME_RATE),
+        );
+        if let Some(frame_time) = self.frame_timer.take() {
+            if self
+                .post_processing
+                .update_render_scale(&self.device, frame_time, target_frame_time)
+            {
+                self.resize_scene();
+            }
         }
 
         self.frame_number += 1;
@@ -0,0 +1,189 @@
+// temporal anti-aliasing, for while the camera moves
+// every frame the scene's rays go through a different point inside their pixel, and the frame
+// is blended into the last output reprojected with the last frame's camera
+// the history is clamped to the colours around each pixel so it can't smear what moved
+
+use std::sync::Arc;
+
+use glam::{vec2, vec3, Mat3, Vec2};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{
+    camera::Camera,
+    fullscreen_pass::FullscreenPass,
+    otheruniforms::BufferContent,
+    progressive::halton,
+    render_graph::{RenderGraph, Target, TextureHandle, TextureSize},
+};
+
+// how many jitter positions there are before they repeat
+const JITTER_PHASES: u32 = 8;
+
+// how much of each frame goes into the output once there's a history
+const BLEND: f32 = 0.1;
+
+// a clip position with 1 as z to the camera space direction the scene's rays go in
+// see Camera::clip_position_with_tansform_to_direction
+fn clip_to_camera(tan_fov_half: Vec2) -> Mat3 {
+    Mat3::from_diagonal(vec3(-tan_fov_half.x, tan_fov_half.y, -1.0))
+}
+
+// the inverse of clip_to_camera, up to a scale
+fn camera_to_clip(tan_fov_half: Vec2) -> Mat3 {
+    Mat3::from_diagonal(vec3(1.0 / tan_fov_half.x, -1.0 / tan_fov_half.y, 1.0))
+}
+
+// the checks encase's derive adds next to the struct are never called, so the allow has to be
+// on a module around it
+#[allow(dead_code)]
+mod uniform {
+    use encase::ShaderType;
+    use glam::Mat3;
+
+    #[derive(ShaderType)]
+    pub struct TaaUniform {
+        pub(super) reprojection: Mat3,
+        pub(super) blend: f32,
+    }
+}
+
+pub use uniform::TaaUniform;
+
+// what the last frame was rendered with
+struct View {
+    world_to_camera: Mat3,
+    tan_fov_half: Vec2,
+}
+
+pub struct Taa {
+    // changing this changes the graph, PostProcessing rebuilds it
+    enabled: bool,
+    // which jitter position this frame is at, from 1 to JITTER_PHASES
+    jitter_phase: u32,
+    // none right after a reset, when there's no history to reproject
+    previous_view: Option<View>,
+
+    pub uniform_buffer: Arc<wgpu::Buffer>,
+}
+
+impl Taa {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let uniform = TaaUniform {
+            reprojection: Mat3::IDENTITY,
+            blend: 1.0,
+        };
+        let uniform_buffer = Arc::new(device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("taa uniform buffer"),
+                contents: &uniform.uniform_buffer_content(),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            },
+        ));
+        Self {
+            enabled: false,
+            jitter_phase: 0,
+            previous_view: None,
+
+            uniform_buffer,
+        }
+    }
+
+    // declares input anti-aliased over time
+    // returns the texture holding it
+    pub fn add_to_graph(
+        &self,
+        graph: &mut RenderGraph,
+        device: &wgpu::Device,
+        label: &str,
+        input: TextureHandle,
+    ) -> TextureHandle {
+        let format = graph.format(input);
+        let resolved =
+            graph.add_texture(&format!("{} taa texture", label), TextureSize::Full, format);
+        let history = graph.add_history(resolved);
+        let pass_label = format!("{} taa", label);
+        graph.add_pass(
+            &pass_label,
+            &[input, history],
+            &[Target::Texture(resolved)],
+            FullscreenPass::new(
+                device,
+                &pass_label,
+                include_shader!("src/taa.wgsl"),
+                2,
+                vec![self.uniform_buffer.clone()],
+                format,
+            ),
+        );
+        resolved
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.reset();
+        println!("taa: {}", if enabled { "on" } else { "off" });
+    }
+
+    // for when the history is gone, after a resize or a rebuild
+    pub fn reset(&mut self) {
+        self.jitter_phase = 0;
+        self.previous_view = None;
+    }
+
+    // has to be called once a frame before rendering, with the camera the frame renders with
+    // returns where in its pixel this frame's rays go through, from -0.5 to 0.5
+    pub fn next_frame(&mut self, queue: &wgpu::Queue, camera: &Camera) -> Vec2 {
+        let view = View {
+            world_to_camera: Mat3::from_mat4(camera.rot_matrix()).transpose(),
+            tan_fov_half: camera.tan_fov_half(),
+        };
+        let camera_to_world = view.world_to_camera.transpose();
+
+        let uniform = match &self.previous_view {
+            Some(previous_view) => TaaUniform {
+                reprojection: camera_to_clip(previous_view.tan_fov_half)
+                    * previous_view.world_to_camera
+                    * camera_to_world
+                    * clip_to_camera(view.tan_fov_half),
+                blend: BLEND,
+            },
+            None => TaaUniform {
+                reprojection: Mat3::IDENTITY,
+                blend: 1.0,
+            },
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, &uniform.uniform_buffer_content());
+        self.previous_view = Some(view);
+
+        self.jitter_phase = self.jitter_phase % JITTER_PHASES + 1;
+        vec2(halton(self.jitter_phase, 2), halton(self.jitter_phase, 3)) - 0.5
+    }
+
+    // g toggles it, which needs the graph rebuilding
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::KeyG),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                self.set_enabled(!self.enabled);
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+#include "src/fullscreen.wgsl"
+
+struct TaaUniform {
+    // this frame's clip position, with 1 as z, to last frame's clip position times some w
+    // w is negative for directions that were in front of last frame's camera
+    reprojection: mat3x3f,
+    // how much of this frame goes into the output, 1 right after a reset
+    blend: f32,
+};
+
+@group(0) @binding(0)
+var frame_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var frame_texture_sampler: sampler;
+@group(0) @binding(2)
+var history_texture: texture_2d<f32>;
+@group(0) @binding(3)
+var history_texture_sampler: sampler;
+@group(0) @binding(4)
+var<uniform> taa: TaaUniform;
+
+@fragment
+fn main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2f(textureDimensions(frame_texture));
+    let frame = textureSampleLevel(frame_texture, frame_texture_sampler, in.texcoord, 0.0);
+
+    // the colours around this pixel this frame, history outside them is stale
+    var neighbourhood_min = frame;
+    var neighbourhood_max = frame;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2f(f32(x), f32(y)) * texel;
+            let neighbour = textureSampleLevel(frame_texture, frame_texture_sampler, in.texcoord + offset, 0.0);
+            neighbourhood_min = min(neighbourhood_min, neighbour);
+            neighbourhood_max = max(neighbourhood_max, neighbour);
+        }
+    }
+
+    // the scene has no depth, so this reprojects the ray direction as if everything was at infinity
+    let clip = vec2f(in.texcoord.x * 2.0 - 1.0, 1.0 - in.texcoord.y * 2.0);
+    let previous = taa.reprojection * vec3f(clip, 1.0);
+    let previous_clip = previous.xy / previous.z;
+    let previous_texcoord = vec2f(previous_clip.x * 0.5 + 0.5, 0.5 - previous_clip.y * 0.5);
+
+    let on_screen = previous.z < 0.0
+        && all(previous_texcoord >= vec2f(0.0))
+        && all(previous_texcoord <= vec2f(1.0));
+    if !on_screen {
+        return frame;
+    }
+
+    let history = textureSampleLevel(history_texture, history_texture_sampler, previous_texcoord, 0.0);
+    let clamped_history = clamp(history, neighbourhood_min, neighbourhood_max);
+    return mix(clamped_history, frame, taa.blend);
+}
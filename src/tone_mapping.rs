@@ -0,0 +1,193 @@
+// maps the hdr output of the bloom chain to the surface
+// the last pass in the render graph, it writes to the graph's output
+
+use std::sync::Arc;
+
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{
+    fullscreen_pass::FullscreenPass,
+    otheruniforms::BufferContent,
+    render_graph::{RenderGraph, Target, TextureHandle},
+    settings::number_from_virtual_key_code,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMappingCurve {
+    // just clamps to 1
+    Linear = 0,
+    Reinhard = 1,
+    AcesFilmic = 2,
+    AgX = 3,
+}
+
+impl ToneMappingCurve {
+    pub const ALL: [Self; 4] = [Self::Linear, Self::Reinhard, Self::AcesFilmic, Self::AgX];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Reinhard => "reinhard",
+            Self::AcesFilmic => "aces",
+            Self::AgX => "agx",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|curve| curve.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+// the checks encase's derive adds next to the struct are never called, so the allow has to be
+// on a module around it
+#[allow(dead_code)]
+mod uniform {
+    use encase::ShaderType;
+
+    #[derive(Debug, ShaderType)]
+    pub struct ToneMappingUniform {
+        pub(super) curve: u32,
+        // in stops, 0 leaves the image as it is
+        pub(super) exposure: f32,
+    }
+}
+
+pub use uniform::ToneMappingUniform;
+
+// how much [ and ] change the exposure by
+const EXPOSURE_STEP: f32 = 0.5;
+
+// the settings and their uniform, shared with the pass add_to_graph declares
+pub struct ToneMapping {
+    pub curve: ToneMappingCurve,
+    pub exposure: f32,
+
+    pub uniform_buffer: Arc<wgpu::Buffer>,
+
+    // while t is held the number keys pick the curve
+    curve_modifier_pressed: bool,
+}
+
+impl ToneMapping {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let curve = ToneMappingCurve::AcesFilmic;
+        let exposure = 0.0;
+
+        let uniform_buffer = Arc::new(device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("tone mapping uniform buffer"),
+                contents: &Self::uniform(curve, exposure).uniform_buffer_content(),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            },
+        ));
+
+        Self {
+            curve,
+            exposure,
+
+            uniform_buffer,
+
+            curve_modifier_pressed: false,
+        }
+    }
+
+    // declares the pass mapping input onto the graph's output
+    pub fn add_to_graph(
+        &self,
+        graph: &mut RenderGraph,
+        device: &wgpu::Device,
+        input: TextureHandle,
+        output_format: wgpu::TextureFormat,
+    ) {
+        graph.add_pass(
+            "tone mapping",
+            &[input],
+            &[Target::Output],
+            FullscreenPass::new(
+                device,
+                "tone mapping",
+                include_shader!("src/tone_mapping.wgsl"),
+                1,
+                vec![self.uniform_buffer.clone()],
+                output_format,
+            ),
+        );
+    }
+
+    fn uniform(curve: ToneMappingCurve, exposure: f32) -> ToneMappingUniform {
+        ToneMappingUniform {
+            curve: curve as u32,
+            exposure,
+        }
+    }
+
+    // has to be called after curve or exposure are changed
+    pub fn write_uniform(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            &Self::uniform(self.curve, self.exposure).uniform_buffer_content(),
+        );
+    }
+
+    pub fn set_curve(&mut self, curve: ToneMappingCurve, queue: &wgpu::Queue) {
+        self.curve = curve;
+        println!("tone mapping: {}", curve.name());
+        self.write_uniform(queue);
+    }
+
+    pub fn set_exposure(&mut self, exposure: f32, queue: &wgpu::Queue) {
+        self.exposure = exposure;
+        println!("exposure: {} stops", exposure);
+        self.write_uniform(queue);
+    }
+
+    pub fn process_event(&mut self, event: &WindowEvent, queue: &wgpu::Queue) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(code),
+                        state,
+                        ..
+                    },
+                ..
+            } => {
+                let is_pressed = *state == ElementState::Pressed;
+                match code {
+                    KeyCode::KeyT => {
+                        self.curve_modifier_pressed = is_pressed;
+                        true
+                    }
+                    KeyCode::BracketLeft if is_pressed => {
+                        self.set_exposure(self.exposure - EXPOSURE_STEP, queue);
+                        true
+                    }
+                    KeyCode::BracketRight if is_pressed => {
+                        self.set_exposure(self.exposure + EXPOSURE_STEP, queue);
+                        true
+                    }
+                    _ if is_pressed && self.curve_modifier_pressed => {
+                        match number_from_virtual_key_code(code)
+                            .and_then(|number| ToneMappingCurve::ALL.get(number))
+                        {
+                            Some(&curve) => {
+                                self.set_curve(curve, queue);
+                                true
+                            }
+                            None => false,
+                        }
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,95 @@
+#include "src/fullscreen.wgsl"
+
+struct ToneMapping {
+    // 0 linear clamp, 1 reinhard, 2 aces filmic, 3 agx
+    curve: u32,
+    // in stops
+    exposure: f32,
+}
+
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_texture_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> tone_mapping: ToneMapping;
+
+fn reinhard(col: vec3f) -> vec3f {
+    return col / (col + 1.0);
+}
+
+// stephen hill's fit of the aces rrt and odt
+// the matrices go from srgb into the fit's space and back, they're column major
+const ACES_INPUT = mat3x3f(
+    0.59719, 0.07600, 0.02840,
+    0.35458, 0.90834, 0.13383,
+    0.04823, 0.01566, 0.83777,
+);
+const ACES_OUTPUT = mat3x3f(
+    1.60475, -0.10208, -0.00327,
+    -0.53108, 1.10813, -0.07276,
+    -0.07367, -0.00605, 1.07602,
+);
+
+fn rrt_and_odt_fit(v: vec3f) -> vec3f {
+    let a = v * (v + 0.0245786) - 0.000090537;
+    let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+    return a / b;
+}
+
+fn aces(col: vec3f) -> vec3f {
+    return ACES_OUTPUT * rrt_and_odt_fit(ACES_INPUT * col);
+}
+
+// agx with the default look, from benjamin wrensch's minimal version
+// https://iolite-engine.com/blog_posts/minimal_agx_implementation
+const AGX_INSET = mat3x3f(
+    0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+    0.0784335999999992, 0.878468636469772, 0.0784336,
+    0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+);
+const AGX_OUTSET = mat3x3f(
+    1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+    -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+    -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+);
+const AGX_MIN_EV = -12.47393;
+const AGX_MAX_EV = 4.026069;
+
+// polynomial fit of the agx sigmoid
+fn agx_contrast(x: vec3f) -> vec3f {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn agx(col: vec3f) -> vec3f {
+    var v = AGX_INSET * col;
+    v = clamp(log2(max(v, vec3f(1e-10))), vec3f(AGX_MIN_EV), vec3f(AGX_MAX_EV));
+    v = (v - AGX_MIN_EV) / (AGX_MAX_EV - AGX_MIN_EV);
+    v = AGX_OUTSET * agx_contrast(v);
+    // the curve bakes in a 2.2 gamma, and the surface does its own srgb encoding
+    return pow(max(v, vec3f(0.0)), vec3f(2.2));
+}
+
+@fragment
+fn main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let col = textureSampleLevel(input_texture, input_texture_sampler, in.texcoord, 0.0).rgb;
+    let exposed = col * exp2(tone_mapping.exposure);
+    var mapped: vec3f;
+    switch tone_mapping.curve {
+        case 1u: {
+            mapped = reinhard(exposed);
+        }
+        case 2u: {
+            mapped = aces(exposed);
+        }
+        case 3u: {
+            mapped = agx(exposed);
+        }
+        default: {
+            mapped = exposed;
+        }
+    }
+    return vec4<f32>(clamp(mapped, vec3f(0.0), vec3f(1.0)), 1.0);
+}
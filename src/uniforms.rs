@@ -4,6 +4,8 @@ use winit::dpi::PhysicalSize;
 
 use glam::{vec2, vec3, vec4, Mat4, Vec2, Vec3, Vec4};
 
+use std::time::Duration;
+
 use encase::ShaderType;
 
 use crate::camera::Camera;
@@ -103,6 +105,8 @@ pub struct CameraUniform {
     // has to be vec4 for correct array stride
     screen_space_screen_triangle: [Vec4; 3],
     pos_to_world_space_screen_triangle: [Vec4; 3],
+    // seconds since the start, for anything that moves by itself
+    time: f32,
 }
 
 impl CameraUniform {
@@ -118,19 +122,28 @@ impl CameraUniform {
             ],
             // screen_space_screen_triangle: vec2(0.0, 0.0),
             pos_to_world_space_screen_triangle: [Vec4::ZERO; 3],
+            time: 0.0,
         }
     }
-    pub fn update(&mut self, camera: &Camera) {
+    // jitter moves every ray by the same amount in clip space, without moving the triangle
+    pub fn update(&mut self, camera: &Camera, jitter: Vec2) {
         self.pos = camera.pos;
         // self.view_proj = camera.build_view_projection_matrix();
         // self.inverse_view_proj = self.view_proj.inverse();
 
         self.pos_to_world_space_screen_triangle = camera
             .pos_to_world_space_screen_triangle(
-                self.screen_space_screen_triangle.map(|v| vec2(v.x, v.y)),
+                self.screen_space_screen_triangle
+                    .map(|v| vec2(v.x, v.y) + jitter),
             )
             .map(|v| vec4(v.x, v.y, v.z, 0.0));
     }
+    pub fn advance_time(&mut self, delta_time: Duration) {
+        self.time += delta_time.as_secs_f32();
+    }
+    pub fn time(&self) -> f32 {
+        self.time
+    }
 }
 
 // We need this for Rust to store our data correctly for the shaders
@@ -118,6 +118,13 @@ impl Increment<i32> for usize {
     }
 }
 
+// used for enum-like uniforms, so it stops at 0 instead of wrapping
+impl Increment<i32> for u32 {
+    fn increment(&self, other: &i32) -> Self {
+        ((*self as i32) + other).max(0) as u32
+    }
+}
+
 impl Opposite<Self> for i32 {
     fn opposite(&self) -> Self {
         -self
@@ -0,0 +1,12 @@
+#include "src/fullscreen.wgsl"
+
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_texture_sampler: sampler;
+
+@fragment
+fn main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // bilinear, the sampler filters linearly
+    return textureSampleLevel(input_texture, input_texture_sampler, in.texcoord, 0.0);
+}